@@ -0,0 +1,183 @@
+//! Working out the tag that follows the latest tag by bumping its version
+
+use crate::conventional::CommitCounts;
+use crate::selection::{TagFilter, format_tag, parse_tag_version};
+use clap::ValueEnum;
+use semver::Version;
+
+/// The version component to increment when computing the next tag
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Bump {
+    Major,
+    Minor,
+    #[default]
+    Patch,
+    /// Choose the component from the conventional commits since the latest tag
+    Auto,
+}
+
+/// Choose the version component to bump from the conventional commits since a release
+///
+/// Breaking changes bump the major version, features the minor version and anything else the
+/// patch. While the major version is 0 the minor version acts as the major one, so unless
+/// `strict` is set breaking changes bump the minor version and features the patch.
+/// # Arguments
+/// * `counts` - The kinds of commits since the release
+/// * `version` - The version of the release
+/// * `strict` - Follow the rules for 1.0.0 and above even for 0.x versions
+/// # Returns
+/// The component to bump, never `Bump::Auto`
+pub fn get_commit_bump(counts: &CommitCounts, version: &Version, strict: bool) -> Bump {
+    let pre_stable = version.major == 0 && !strict;
+    if counts.breaking > 0 {
+        if pre_stable { Bump::Minor } else { Bump::Major }
+    } else if counts.feat > 0 && !pre_stable {
+        Bump::Minor
+    } else {
+        Bump::Patch
+    }
+}
+
+/// Work out the tag that follows the latest tag
+/// # Arguments
+/// * `latest_tag` - The latest tag
+/// * `filter` - The filter describing how versions are written in the tags
+/// * `bump` - The version component to increment, where `Bump::Auto` bumps the patch as it
+///   should be resolved with `get_commit_bump` first
+/// * `finalize` - Release a prerelease as its stable version instead of bumping it
+/// # Returns
+/// The next tag (e.g. `v1.3.0` for a minor bump of `v1.2.4`, or `v1.2.0` when finalizing
+/// `v1.2.0-rc.3`), or `None` if the latest tag is not a valid semantic version
+pub fn get_next_tag(
+    latest_tag: &str,
+    filter: &TagFilter,
+    bump: Bump,
+    finalize: bool,
+) -> Option<String> {
+    let version = parse_tag_version(latest_tag, filter)?;
+    let next = if finalize && !version.pre.is_empty() {
+        Version::new(version.major, version.minor, version.patch)
+    } else {
+        bump_version(&version, bump)
+    };
+    Some(format_tag(&next, filter))
+}
+
+/// Increment a component of a version
+/// # Arguments
+/// * `version` - The version to bump
+/// * `bump` - The component to increment, where `Bump::Auto` bumps the patch
+/// # Returns
+/// The bumped core version, without prerelease or build metadata
+pub fn bump_version(version: &Version, bump: Bump) -> Version {
+    match bump {
+        Bump::Major => Version::new(version.major + 1, 0, 0),
+        Bump::Minor => Version::new(version.major, version.minor + 1, 0),
+        Bump::Patch | Bump::Auto => Version::new(version.major, version.minor, version.patch + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(tag_prefix: &str, prerelease_suffix: &str, prerelease: bool) -> TagFilter {
+        TagFilter {
+            tag_prefixes: vec![tag_prefix.to_string()],
+            prerelease_suffix: prerelease_suffix.to_string(),
+            prerelease,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_next_tag() {
+        let stable_filter = filter("v", "rc", false);
+
+        // Check each bump of a stable tag
+        assert_eq!(
+            get_next_tag("v1.2.4", &stable_filter, Bump::Major, false).unwrap(),
+            "v2.0.0"
+        );
+        assert_eq!(
+            get_next_tag("v1.2.4", &stable_filter, Bump::Minor, false).unwrap(),
+            "v1.3.0"
+        );
+        assert_eq!(
+            get_next_tag("v1.2.4+build.7", &stable_filter, Bump::Patch, false).unwrap(),
+            "v1.2.5"
+        );
+
+        // Check that finalizing a prerelease releases its core version
+        assert_eq!(
+            get_next_tag("v1.2.0-rc.3", &stable_filter, Bump::Patch, true).unwrap(),
+            "v1.2.0"
+        );
+        assert_eq!(
+            get_next_tag("v1.2.0-rc.3", &stable_filter, Bump::Major, true).unwrap(),
+            "v1.2.0"
+        );
+
+        // Check that finalizing a stable tag falls through to the bump
+        assert_eq!(
+            get_next_tag("v1.2.0", &stable_filter, Bump::Patch, true).unwrap(),
+            "v1.2.1"
+        );
+        assert_eq!(
+            get_next_tag("v1.2.0", &stable_filter, Bump::Minor, true).unwrap(),
+            "v1.3.0"
+        );
+
+        assert!(get_next_tag("vnext", &stable_filter, Bump::Patch, false).is_none());
+    }
+
+    #[test]
+    fn test_get_commit_bump() {
+        let counts = |feat, fix, breaking| CommitCounts {
+            feat,
+            fix,
+            breaking,
+        };
+        let pre_stable = Version::new(0, 3, 4);
+        let stable = Version::new(1, 3, 4);
+
+        // Check that 0.x versions treat the minor version as breaking
+        let bump = get_commit_bump(&counts(1, 0, 1), &pre_stable, false);
+        assert_eq!(bump, Bump::Minor);
+        let stable_filter = filter("v", "rc", false);
+        assert_eq!(
+            get_next_tag("v0.3.4", &stable_filter, bump, false).unwrap(),
+            "v0.4.0"
+        );
+        assert_eq!(
+            get_commit_bump(&counts(2, 1, 0), &pre_stable, false),
+            Bump::Patch
+        );
+        assert_eq!(
+            get_commit_bump(&counts(0, 3, 0), &pre_stable, false),
+            Bump::Patch
+        );
+
+        // Check that strict bumps and 1.x versions follow the usual rules
+        assert_eq!(
+            get_commit_bump(&counts(0, 0, 1), &pre_stable, true),
+            Bump::Major
+        );
+        assert_eq!(
+            get_commit_bump(&counts(1, 0, 0), &pre_stable, true),
+            Bump::Minor
+        );
+        assert_eq!(
+            get_commit_bump(&counts(0, 1, 1), &stable, false),
+            Bump::Major
+        );
+        assert_eq!(
+            get_commit_bump(&counts(1, 1, 0), &stable, false),
+            Bump::Minor
+        );
+        assert_eq!(
+            get_commit_bump(&counts(0, 0, 0), &stable, false),
+            Bump::Patch
+        );
+    }
+}
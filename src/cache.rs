@@ -0,0 +1,221 @@
+//! Caching the commits of tags between runs, so unchanged tags are not resolved again
+
+use git2::{Oid, Repository};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// What a tag resolved to when it was cached
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CachedTag {
+    /// The object the tag reference pointed to, which changes when the tag is moved
+    ref_id: Oid,
+    /// The commit the tag points to
+    sha: Oid,
+    /// The commit time in seconds since the epoch
+    commit_time: i64,
+}
+
+/// The commits of tags, read from and written back to a cache file
+#[derive(Debug)]
+pub struct TagCache {
+    /// The file the cache is read from and written to
+    path: PathBuf,
+    /// The cached tags by name
+    entries: HashMap<String, CachedTag>,
+    /// The number of tags resolved from the repository rather than the cache
+    resolved: usize,
+}
+
+impl TagCache {
+    /// Read a cache file, starting an empty cache if it does not exist yet
+    /// # Arguments
+    /// * `path` - The path of the cache file
+    /// # Returns
+    /// The cache, leaving out any malformed entries
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a JSON object
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => "{}".to_string(),
+            Err(e) => {
+                return Err(format!("Failed to read the cache {}: {e}", path.display()).into());
+            }
+        };
+        let Value::Object(cached) = serde_json::from_str(&contents)? else {
+            return Err(format!("The cache {} is not a JSON object", path.display()).into());
+        };
+
+        let entries = cached
+            .into_iter()
+            .filter_map(|(tag, entry)| {
+                let oid = |field: &str| Oid::from_str(entry[field].as_str()?).ok();
+                let cached_tag = CachedTag {
+                    ref_id: oid("ref")?,
+                    sha: oid("sha")?,
+                    commit_time: entry["commit_time"].as_i64()?,
+                };
+                Some((tag, cached_tag))
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+            resolved: 0,
+        })
+    }
+
+    /// Look up the commit and commit time of each tag, resolving only the tags that are not
+    /// cached or whose reference has changed since
+    ///
+    /// Tags that are not given are dropped from the cache, so it does not grow with deleted tags.
+    /// # Arguments
+    /// * `repository` - The repository containing the tags
+    /// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+    /// * `tags` - The names of the tags
+    /// # Returns
+    /// The commit and its time (in seconds since the epoch) of each resolvable tag
+    pub fn resolve_tags(
+        &mut self,
+        repository: &Repository,
+        ref_namespace: &str,
+        tags: &[&str],
+    ) -> HashMap<String, (Oid, i64)> {
+        let mut entries = HashMap::new();
+        for tag in tags {
+            let Some(ref_id) = repository
+                .find_reference(&format!("{ref_namespace}/{tag}"))
+                .ok()
+                .and_then(|reference| reference.target())
+            else {
+                continue;
+            };
+            let cached_tag = match self.entries.remove(*tag) {
+                Some(cached_tag) if cached_tag.ref_id == ref_id => cached_tag,
+                _ => {
+                    let Some(commit) = repository
+                        .find_object(ref_id, None)
+                        .and_then(|object| object.peel_to_commit())
+                        .ok()
+                    else {
+                        continue;
+                    };
+                    self.resolved += 1;
+                    CachedTag {
+                        ref_id,
+                        sha: commit.id(),
+                        commit_time: commit.time().seconds(),
+                    }
+                }
+            };
+            entries.insert(tag.to_string(), cached_tag);
+        }
+        self.entries = entries;
+
+        self.entries
+            .iter()
+            .map(|(tag, cached_tag)| (tag.clone(), (cached_tag.sha, cached_tag.commit_time)))
+            .collect()
+    }
+
+    /// The number of tags resolved from the repository rather than the cache so far
+    pub fn resolved(&self) -> usize {
+        self.resolved
+    }
+
+    /// Write the cache back to its file
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let mut tags = self.entries.iter().collect::<Vec<_>>();
+        tags.sort_by_key(|(tag, _)| *tag);
+        let cached = tags
+            .into_iter()
+            .map(|(tag, cached_tag)| {
+                let entry = json!({
+                    "ref": cached_tag.ref_id.to_string(),
+                    "sha": cached_tag.sha.to_string(),
+                    "commit_time": cached_tag.commit_time,
+                });
+                (tag.clone(), entry)
+            })
+            .collect::<Map<_, _>>();
+        fs::write(&self.path, serde_json::to_string_pretty(&cached)?)
+            .map_err(|e| format!("Failed to write the cache {}: {e}", self.path.display()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+    use tempfile::TempDir;
+
+    /// Commit to HEAD at a fixed time, returning the new commit
+    fn commit_at(repository: &Repository, seconds: i64) -> Oid {
+        let signature =
+            Signature::new("Test User", "test@example.com", &Time::new(seconds, 0)).unwrap();
+        let tree = repository
+            .find_tree(repository.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parents = repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Commit",
+                &tree,
+                parents.iter().collect::<Vec<_>>().as_slice(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_tags() {
+        let dir = TempDir::new().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let first = commit_at(&repository, 1_700_000_000);
+        let first = repository.find_object(first, None).unwrap();
+        repository.tag_lightweight("v1.0.0", &first, false).unwrap();
+        let second = commit_at(&repository, 1_700_100_000);
+        let second = repository.find_object(second, None).unwrap();
+        repository
+            .tag_lightweight("v1.1.0", &second, false)
+            .unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let tags = ["v1.0.0", "v1.1.0"];
+
+        // Check that a cold cache resolves every tag
+        let mut cache = TagCache::load(&cache_path).unwrap();
+        let resolved = cache.resolve_tags(&repository, "refs/tags", &tags);
+        assert_eq!(resolved["v1.0.0"], (first.id(), 1_700_000_000));
+        assert_eq!(resolved["v1.1.0"], (second.id(), 1_700_100_000));
+        assert_eq!(cache.resolved(), 2);
+        cache.save().unwrap();
+
+        // Check that a warm cache resolves nothing
+        let mut cache = TagCache::load(&cache_path).unwrap();
+        assert_eq!(
+            cache.resolve_tags(&repository, "refs/tags", &tags),
+            resolved
+        );
+        assert_eq!(cache.resolved(), 0);
+        cache.save().unwrap();
+
+        // Check that a moved tag is resolved again
+        repository.tag_lightweight("v1.0.0", &second, true).unwrap();
+        let mut cache = TagCache::load(&cache_path).unwrap();
+        let resolved = cache.resolve_tags(&repository, "refs/tags", &tags);
+        assert_eq!(resolved["v1.0.0"], (second.id(), 1_700_100_000));
+        assert_eq!(cache.resolved(), 1);
+    }
+}
@@ -0,0 +1,135 @@
+//! Parsing commit messages written in the Conventional Commits format
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Matches the header of a conventional commit, e.g. `feat(parser)!: add arrays`
+static HEADER_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?<kind>[A-Za-z][A-Za-z0-9-]*)(?:\((?<scope>[^()\r\n]*)\))?(?<breaking>!)?: \S")
+        .unwrap()
+});
+
+/// The parts of a conventional commit message used to classify it
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// The lowercase commit type (e.g. `feat` or `fix`)
+    pub kind: String,
+    /// The scope in parentheses after the type, if any
+    pub scope: Option<String>,
+    /// Whether the commit is marked with `!` or has a `BREAKING CHANGE` footer
+    pub breaking: bool,
+}
+
+/// The number of commits of each kind relevant to a release
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CommitCounts {
+    /// Commits of type `feat`
+    pub feat: usize,
+    /// Commits of type `fix`
+    pub fix: usize,
+    /// Breaking changes of any type
+    pub breaking: usize,
+}
+
+/// Parse a commit message written in the Conventional Commits format
+/// # Arguments
+/// * `message` - The full commit message
+/// # Returns
+/// The type, scope and whether it is a breaking change, or `None` if the header does not follow
+/// the format
+pub fn parse_commit_message(message: &str) -> Option<ConventionalCommit> {
+    let mut lines = message.lines();
+    let captures = HEADER_PATTERN.captures(lines.next()?)?;
+
+    let footer_breaking = lines
+        .any(|line| line.starts_with("BREAKING CHANGE: ") || line.starts_with("BREAKING-CHANGE: "));
+
+    Some(ConventionalCommit {
+        kind: captures["kind"].to_lowercase(),
+        scope: captures
+            .name("scope")
+            .map(|scope| scope.as_str().to_string()),
+        breaking: captures.name("breaking").is_some() || footer_breaking,
+    })
+}
+
+/// Count the features, fixes and breaking changes among commit messages
+/// # Arguments
+/// * `messages` - The commit messages; those not in the Conventional Commits format are ignored
+/// # Returns
+/// The counts, where a breaking feature or fix counts towards both its type and `breaking`
+pub fn count_commits<'a>(messages: impl IntoIterator<Item = &'a str>) -> CommitCounts {
+    let mut counts = CommitCounts::default();
+    for commit in messages.into_iter().filter_map(parse_commit_message) {
+        match commit.kind.as_str() {
+            "feat" => counts.feat += 1,
+            "fix" => counts.fix += 1,
+            _ => {}
+        }
+        if commit.breaking {
+            counts.breaking += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commit_message() {
+        assert_eq!(
+            parse_commit_message("feat(parser): add arrays"),
+            Some(ConventionalCommit {
+                kind: "feat".to_string(),
+                scope: Some("parser".to_string()),
+                breaking: false,
+            })
+        );
+        assert_eq!(
+            parse_commit_message("Fix!: drop the old flag\n\nIt was deprecated"),
+            Some(ConventionalCommit {
+                kind: "fix".to_string(),
+                scope: None,
+                breaking: true,
+            })
+        );
+
+        // Check that breaking change footers are recognised
+        let commit = parse_commit_message(
+            "refactor: rename outputs\n\nBREAKING CHANGE: tag is now latest_tag",
+        )
+        .unwrap();
+        assert!(commit.breaking);
+        let commit = parse_commit_message("chore: note\n\nbreaking change: lowercase").unwrap();
+        assert!(!commit.breaking);
+
+        // Check that other messages are ignored
+        assert_eq!(parse_commit_message("Update README"), None);
+        assert_eq!(parse_commit_message("feat:missing space"), None);
+        assert_eq!(parse_commit_message("Merge branch 'main': sync"), None);
+        assert_eq!(parse_commit_message(""), None);
+    }
+
+    #[test]
+    fn test_count_commits() {
+        let messages = [
+            "feat: add the channels subcommand",
+            "feat(output)!: write JSON by default",
+            "fix: handle detached HEAD",
+            "fix(remote): retry failed connections\n\nBREAKING-CHANGE: the token is required",
+            "fix: typo",
+            "docs: explain prefixes\n\nBREAKING CHANGE: none really",
+            "Merge pull request #12",
+        ];
+        assert_eq!(
+            count_commits(messages),
+            CommitCounts {
+                feat: 2,
+                fix: 3,
+                breaking: 3,
+            }
+        );
+    }
+}
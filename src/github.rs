@@ -0,0 +1,253 @@
+//! Reading releases and topics from the GitHub REST API
+
+use clap::ValueEnum;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The number of items requested per page of a list endpoint (the most GitHub allows)
+const PAGE_SIZE: usize = 100;
+
+/// The prefix of the repository topics that name prerelease channels (e.g. `channel-beta`)
+const CHANNEL_TOPIC_PREFIX: &str = "channel-";
+
+/// The places the prerelease channels can be read from instead of `--channels`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChannelSource {
+    /// The topics of the GitHub repository named `channel-<name>`
+    Github,
+}
+
+/// Sends GET requests to the GitHub REST API
+pub trait GithubApi {
+    /// Get the body of a response
+    /// # Arguments
+    /// * `path` - The path and query of the endpoint (e.g. `/repos/owner/name/releases`)
+    /// # Errors
+    /// Returns an error if the request fails or the response is not successful
+    fn get(&self, path: &str) -> Result<String, Box<dyn Error>>;
+}
+
+/// Sends requests with `curl`, authenticated with a token if one is available
+pub struct CurlApi {
+    api_url: String,
+    token: Option<String>,
+}
+
+impl CurlApi {
+    /// Configure the API from the GitHub actions environment
+    ///
+    /// `GITHUB_API_URL` selects the server (defaulting to github.com) and `GITHUB_TOKEN`
+    /// authenticates the requests if it is set.
+    pub fn from_env() -> Self {
+        Self {
+            api_url: env::var("GITHUB_API_URL")
+                .unwrap_or_else(|_| "https://api.github.com".to_string()),
+            token: env::var("GITHUB_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+impl GithubApi for CurlApi {
+    fn get(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        let url = format!("{}{path}", self.api_url.trim_end_matches('/'));
+        // The token is passed on stdin so it does not show up in the process list
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .args(["--header", "Accept: application/vnd.github+json"])
+            .args(["--header", "X-GitHub-Api-Version: 2022-11-28"])
+            .args(["--header", "@-"])
+            .arg(&url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run curl: {e}"))?;
+        if let Some(mut stdin) = child.stdin.take()
+            && let Some(token) = &self.token
+        {
+            writeln!(stdin, "Authorization: Bearer {token}")?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Request to {url} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Get the name of the repository the workflow runs in
+/// # Returns
+/// The repository as `owner/name`
+/// # Errors
+/// Returns an error if `GITHUB_REPOSITORY` is not set
+pub fn get_repository_name() -> Result<String, Box<dyn Error>> {
+    env::var("GITHUB_REPOSITORY")
+        .map_err(|_| "GITHUB_REPOSITORY environment variable missing.".into())
+}
+
+/// Get the tags that have a published release
+///
+/// Draft releases are not published and are left out, while prereleases are included.
+/// # Arguments
+/// * `api` - The API to send the requests to
+/// * `repository` - The repository as `owner/name`
+/// # Returns
+/// The names of the tags of the published releases
+/// # Errors
+/// Returns an error if a request fails or a response is not a list of releases
+pub fn get_published_tags(
+    api: &impl GithubApi,
+    repository: &str,
+) -> Result<HashSet<String>, Box<dyn Error>> {
+    let mut tags = HashSet::new();
+    for page in 1.. {
+        let body = api.get(&format!(
+            "/repos/{repository}/releases?per_page={PAGE_SIZE}&page={page}"
+        ))?;
+        let releases: Value = serde_json::from_str(&body)?;
+        let releases = releases
+            .as_array()
+            .ok_or_else(|| format!("Unexpected response listing the releases of {repository}"))?;
+
+        tags.extend(
+            releases
+                .iter()
+                .filter(|release| release["draft"] == false)
+                .filter_map(|release| release["tag_name"].as_str())
+                .map(str::to_string),
+        );
+        if releases.len() < PAGE_SIZE {
+            break;
+        }
+    }
+    Ok(tags)
+}
+
+/// Get the prerelease channels configured as repository topics
+///
+/// Topics are lowercase, so `channel-rc` configures the `rc` channel. Other topics are ignored.
+/// # Arguments
+/// * `api` - The API to send the request to
+/// * `repository` - The repository as `owner/name`
+/// # Returns
+/// The channels in the order of the topics
+/// # Errors
+/// Returns an error if the request fails or the response does not list topic names
+pub fn get_topic_channels(
+    api: &impl GithubApi,
+    repository: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let body = api.get(&format!("/repos/{repository}/topics"))?;
+    let topics: Value = serde_json::from_str(&body)?;
+    let names = topics["names"]
+        .as_array()
+        .ok_or_else(|| format!("Unexpected response listing the topics of {repository}"))?;
+
+    Ok(names
+        .iter()
+        .filter_map(Value::as_str)
+        .filter_map(|name| name.strip_prefix(CHANNEL_TOPIC_PREFIX))
+        .filter(|channel| !channel.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Answers requests from canned responses, recording the paths requested
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockApi {
+    pub responses: std::collections::HashMap<String, String>,
+    pub requests: std::cell::RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl GithubApi for MockApi {
+    fn get(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        self.requests.borrow_mut().push(path.to_string());
+        self.responses
+            .get(path)
+            .cloned()
+            .ok_or_else(|| format!("No response for {path}").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_published_tags() {
+        let mut api = MockApi::default();
+        api.responses.insert(
+            "/repos/owner/tool/releases?per_page=100&page=1".to_string(),
+            r#"[
+                {"tag_name": "v1.2.0", "draft": true, "prerelease": false},
+                {"tag_name": "v1.1.0", "draft": false, "prerelease": false},
+                {"tag_name": "v1.1.0-rc.1", "draft": false, "prerelease": true}
+            ]"#
+            .to_string(),
+        );
+
+        let tags = get_published_tags(&api, "owner/tool").unwrap();
+        assert_eq!(
+            tags,
+            HashSet::from(["v1.1.0".to_string(), "v1.1.0-rc.1".to_string()])
+        );
+        assert_eq!(api.requests.borrow().len(), 1);
+
+        // Check that an unexpected response is reported
+        api.responses.insert(
+            "/repos/owner/tool/releases?per_page=100&page=1".to_string(),
+            r#"{"message": "Not Found"}"#.to_string(),
+        );
+        assert!(get_published_tags(&api, "owner/tool").is_err());
+    }
+
+    #[test]
+    fn test_get_published_tags_pages() {
+        let mut api = MockApi::default();
+        let full_page = (0..PAGE_SIZE)
+            .map(|patch| format!(r#"{{"tag_name": "v1.0.{patch}", "draft": false}}"#))
+            .collect::<Vec<_>>();
+        api.responses.insert(
+            "/repos/owner/tool/releases?per_page=100&page=1".to_string(),
+            format!("[{}]", full_page.join(",")),
+        );
+        api.responses.insert(
+            "/repos/owner/tool/releases?per_page=100&page=2".to_string(),
+            "[]".to_string(),
+        );
+
+        let tags = get_published_tags(&api, "owner/tool").unwrap();
+        assert_eq!(tags.len(), PAGE_SIZE);
+        assert_eq!(api.requests.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_get_topic_channels() {
+        let mut api = MockApi::default();
+        api.responses.insert(
+            "/repos/owner/tool/topics".to_string(),
+            r#"{"names": ["rust", "channel-rc", "channel-", "channel-beta", "release-channel-x"]}"#
+                .to_string(),
+        );
+
+        let channels = get_topic_channels(&api, "owner/tool").unwrap();
+        assert_eq!(channels, ["rc", "beta"]);
+
+        api.responses
+            .insert("/repos/owner/tool/topics".to_string(), r#"[]"#.to_string());
+        assert!(get_topic_channels(&api, "owner/tool").is_err());
+    }
+}
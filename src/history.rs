@@ -0,0 +1,148 @@
+//! Reading the history of HEAD since a tag: the commits, their messages and their authors
+
+use git2::{Commit, Oid, Repository};
+use std::collections::BTreeMap;
+
+/// Resolve the commit a tag points to, dereferencing annotated tags
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// The id of the tagged commit, or `None` if the tag cannot be resolved locally
+pub fn resolve_tag_commit(repository: &Repository, ref_namespace: &str, tag: &str) -> Option<Oid> {
+    let reference = repository
+        .find_reference(&format!("{ref_namespace}/{tag}"))
+        .ok()?;
+    Some(reference.peel_to_commit().ok()?.id())
+}
+
+/// Check whether HEAD is the commit a tag points to, as in a build triggered by pushing the tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// `true` if HEAD and the tag resolve to the same commit, `false` otherwise or if either cannot
+/// be resolved
+pub fn is_head_on_tag(repository: &Repository, ref_namespace: &str, tag: &str) -> bool {
+    let head = repository
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map(|commit| commit.id());
+    head.is_ok_and(|head| resolve_tag_commit(repository, ref_namespace, tag) == Some(head))
+}
+
+/// Get the commits on HEAD since a tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag; if it cannot be resolved locally the whole history is used
+/// * `limit` - Stop the walk after this many commits, or `None` to walk them all
+/// * `ignore_merges` - Leave out commits with more than one parent, which do not count towards
+///   the limit
+/// # Returns
+/// The commits, newest first, or `None` if the history cannot be walked
+pub fn get_commits_since<'r>(
+    repository: &'r Repository,
+    ref_namespace: &str,
+    tag: &str,
+    limit: Option<usize>,
+    ignore_merges: bool,
+) -> Option<Vec<Commit<'r>>> {
+    let mut revwalk = repository.revwalk().ok()?;
+    revwalk.push_head().ok()?;
+    if let Some(commit) = resolve_tag_commit(repository, ref_namespace, tag) {
+        revwalk.hide(commit).ok()?;
+    }
+
+    revwalk
+        .map(|id| repository.find_commit(id.ok()?).ok())
+        .filter(|commit| {
+            !ignore_merges
+                || commit
+                    .as_ref()
+                    .is_none_or(|commit| commit.parent_count() <= 1)
+        })
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Get the messages of the commits on HEAD since a tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag; if it cannot be resolved locally the whole history is used
+/// * `limit` - Stop the walk after this many commits, or `None` to walk them all
+/// * `ignore_merges` - Leave out the messages of merge commits
+/// # Returns
+/// The commit messages, newest first, or `None` if the history cannot be walked
+pub fn get_commit_messages_since(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    limit: Option<usize>,
+    ignore_merges: bool,
+) -> Option<Vec<String>> {
+    let commits = get_commits_since(repository, ref_namespace, tag, limit, ignore_merges)?;
+    Some(
+        commits
+            .iter()
+            .map(|commit| String::from_utf8_lossy(commit.message_bytes()).into_owned())
+            .collect(),
+    )
+}
+
+/// Get the authors of the commits on HEAD since a tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag; if it cannot be resolved locally the whole history is used
+/// # Returns
+/// The distinct authors as `Name <email>`, sorted, or `None` if the history cannot be walked.
+/// Authors are told apart by email, ignoring case, and named as in their latest commit
+pub fn get_contributors_since(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+) -> Option<Vec<String>> {
+    let commits = get_commits_since(repository, ref_namespace, tag, None, false)?;
+    let mut contributors = BTreeMap::new();
+    for commit in &commits {
+        let author = commit.author();
+        let email = String::from_utf8_lossy(author.email_bytes()).into_owned();
+        contributors.entry(email.to_lowercase()).or_insert_with(|| {
+            format!("{} <{email}>", String::from_utf8_lossy(author.name_bytes()))
+        });
+    }
+
+    let mut contributors = contributors.into_values().collect::<Vec<_>>();
+    contributors.sort();
+    Some(contributors)
+}
+
+/// List the commits on HEAD since a tag for release notes
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag; if it cannot be resolved locally the whole history is used
+/// * `limit` - List at most this many commits, or `None` to list them all
+/// # Returns
+/// Each commit as `<short-sha> <subject>`, newest first, or `None` if the history cannot be
+/// walked
+pub fn get_commit_list_since(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    limit: Option<usize>,
+) -> Option<Vec<String>> {
+    get_commits_since(repository, ref_namespace, tag, limit, false)?
+        .iter()
+        .map(|commit| {
+            let short_id = commit.as_object().short_id().ok()?;
+            let message = String::from_utf8_lossy(commit.message_bytes()).into_owned();
+            let subject = message.lines().next().unwrap_or_default().trim();
+            Some(format!("{} {subject}", short_id.as_str()?))
+        })
+        .collect()
+}
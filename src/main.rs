@@ -1,160 +1,4250 @@
-use clap::Parser;
-use git2::Repository;
+mod bump;
+mod cache;
+mod conventional;
+#[cfg(feature = "github-api")]
+mod github;
+mod history;
+mod manifest;
+mod output;
+mod range;
+mod release;
+mod selection;
+mod signature;
+mod tagging;
+mod warnings;
+
+use anstream::{eprintln, println};
+use anstyle::{AnsiColor, Style};
+use bump::{Bump, bump_version, get_commit_bump, get_next_tag};
+use cache::TagCache;
+use clap::error::ErrorKind;
+use clap::{ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum};
+use conventional::count_commits;
+use git2::{Cred, Direction, Oid, PushOptions, RemoteCallbacks, Repository, StatusOptions};
+use glob::Pattern;
+use history::{
+    get_commit_list_since, get_commit_messages_since, get_contributors_since, is_head_on_tag,
+    resolve_tag_commit,
+};
+use manifest::read_manifest_version;
+use output::{OutputFormat, Outputs, format_annotation, get_output_targets};
+use range::{RangeSyntax, translate_range};
 use regex::Regex;
+use release::{get_docker_tags, get_previous_tags, get_release_metadata};
+use selection::{
+    Constraint, Selection, TagFilter, TieBreak, break_tie, compare_tags, compare_versions,
+    find_latest_including_prerelease, find_latest_tag, find_selected_tag, format_tag, get_channel,
+    get_latest_tag, get_matching_tags, get_prerelease_channel, get_selected_tag, get_tag_pattern,
+    no_matching_tags_error, parse_tag_version, skip_tag, split_tag_prefix,
+};
+use semver::{Prerelease, Version, VersionReq};
+use serde_json::{Map, Value, json};
+use signature::is_signed_tag;
+#[cfg(feature = "verify-key")]
+use signature::verify_tag;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::error::Error;
-use std::fs::write;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::slice;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tagging::{confirm, create_tag, describe_tag, is_ci, plan_tag};
+
+/// Exit code returned by `--check` when the proposed tag already exists
+const TAG_EXISTS_EXIT_CODE: u8 = 3;
+
+/// The style of warnings in the log
+const WARNING_STYLE: Style = AnsiColor::Yellow.on_default().bold();
+
+/// The style of the selected tag in the log
+const TAG_STYLE: Style = AnsiColor::Green.on_default().bold();
 
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short, long)]
-    release_branch: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(short, long, required = true)]
+    release_branch: Option<String>,
     #[arg(long, default_value = "prerelease")]
     prerelease_suffix: String,
-    #[arg(short, long, default_value = "")]
-    tag_prefix: String,
+    /// On a prerelease branch, use the branch name as the prerelease suffix (e.g.
+    /// feature/login selects and creates feature-login prereleases)
+    #[arg(long)]
+    suffix_from_branch: bool,
+    /// The prefix for the tags; give several to match tags under any of them (e.g. "v,ver")
+    #[arg(short, long, value_delimiter = ',', default_value = "", global = true)]
+    tag_prefix: Vec<String>,
+    /// Detect the tag prefix from the tags that look like versions instead of `--tag-prefix`,
+    /// falling back to no prefix if they do not all share one
+    #[arg(long, global = true)]
+    auto_prefix: bool,
+    /// Ignore any tag whose version is above this ceiling (e.g. accidental v999.0.0 placeholders)
+    #[arg(long)]
+    max_version: Option<Version>,
+    /// Where to read tags from
+    #[arg(long, value_enum, default_value_t = TagSource::Local, global = true)]
+    source: TagSource,
+    /// The remote to query when reading tags with `--source remote`, and to push to with `--push`
+    #[arg(long, default_value = "origin", global = true)]
+    remote: String,
+    /// The notes reference to read version strings from with `--source notes`
+    #[arg(long, default_value = "refs/notes/commits", global = true)]
+    notes_ref: String,
+    /// The ref namespace tags are read from, created in and pushed to
+    #[arg(long, default_value = "refs/tags", global = true)]
+    ref_namespace: String,
+    /// Output every matching tag, newest first, instead of the latest tag
+    #[arg(long)]
+    list: bool,
+    /// With `--list`, follow each tag with a tab and its canonical version (e.g. 1.2.3 after
+    /// v01.2.3), whose semver order is the order of the list
+    #[arg(long, requires = "list")]
+    emit_sort_key: bool,
+    /// Output the latest stable tag on each local or remote branch matching this glob as JSON
+    /// (e.g. "release/*")
+    #[arg(long, value_name = "GLOB", value_parser = Pattern::new)]
+    per_branch: Option<Pattern>,
+    /// Output the latest stable tag and the latest tag of every prerelease channel as JSON
+    #[arg(long)]
+    all_channels: bool,
+    /// With `--list` or `--all-channels`, also output every matching tag grouped by channel as
+    /// JSON, newest first within each channel
+    #[arg(long)]
+    group_by_channel: bool,
+    /// The prerelease channels reported by `--all-channels` (defaults to the prerelease suffix)
+    #[arg(long, value_delimiter = ',')]
+    channels: Vec<String>,
+    /// Read the channels from the topics named `channel-<name>` of the GitHub repository in
+    /// GITHUB_REPOSITORY instead of `--channels`
+    #[cfg(feature = "github-api")]
+    #[arg(long, value_enum, value_name = "SOURCE", conflicts_with = "channels")]
+    channels_from: Option<github::ChannelSource>,
+    /// Verify that a proposed tag does not exist yet, exiting with code 3 if it does
+    #[arg(long, value_name = "TAG")]
+    check: Option<String>,
+    /// Only consider tags with this major version (e.g. 1 for the latest of the 1.x line)
+    #[arg(long, value_name = "N")]
+    major: Option<u64>,
+    /// Search for the repository from this directory instead of the current working directory
+    #[arg(long, value_name = "DIR", global = true)]
+    repo_path: Option<PathBuf>,
+    /// Output the Docker image tags derived from the latest tag (e.g. 1, 1.4, 1.4.2, latest)
+    #[arg(long)]
+    docker_tags: bool,
+    /// Output the number of distinct major and minor release lines among the stable tags
+    #[arg(long)]
+    count_release_lines: bool,
+    /// The formats the outputs are written in, repeated or comma separated (e.g. "github,json")
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "github",
+        global = true
+    )]
+    format: Vec<OutputFormat>,
+    /// Write the outputs to this file instead of the GITHUB_OUTPUT file; with several formats,
+    /// repeat it once for each format other than github, in order
+    #[arg(long, value_name = "PATH", global = true)]
+    output_file: Vec<PathBuf>,
+    /// Output the warnings of the run (e.g. skipped tags and fallbacks) as a JSON list
+    #[arg(long)]
+    warnings_json: bool,
+    /// Output `latest_tag` with this prefix in place of the one it matched (e.g. "release-" to
+    /// write v1.2.3 as release-1.2.3)
+    #[arg(long, value_name = "PREFIX")]
+    output_prefix_override: Option<String>,
+    /// Put this text before the name of every output (e.g. "linux_" in a job matrix)
+    #[arg(long, value_name = "PREFIX", default_value = "", global = true)]
+    output_prefix: String,
+    /// Output an abbreviated `latest_tag_sha` that is still unambiguous in the repository
+    #[arg(long, overrides_with = "long_sha")]
+    short_sha: bool,
+    /// Output the full `latest_tag_sha` (the default)
+    #[arg(long, overrides_with = "short_sha")]
+    long_sha: bool,
+    /// The minimum length of an abbreviated `latest_tag_sha` (defaults to core.abbrev, or 7)
+    #[arg(long, value_name = "LENGTH", requires = "short_sha", value_parser = clap::value_parser!(u8).range(4..=40))]
+    abbrev: Option<u8>,
+    /// Output a build number that increases with every commit: the number of commits in the
+    /// history of HEAD
+    #[arg(long)]
+    build_number: bool,
+    /// Output whether the latest prerelease can be promoted, i.e. HEAD has no changes since it
+    #[arg(long)]
+    promotion_check: bool,
+    /// Include the annotation message of the latest tag (null for lightweight tags)
+    #[arg(long)]
+    include_message: bool,
+    /// Output the number of feat, fix and breaking conventional commits since the latest tag
+    #[arg(long)]
+    commit_counts: bool,
+    /// Output whether a conventional commit since the latest stable tag is a breaking change
+    #[arg(long)]
+    breaking_since_stable: bool,
+    /// Only consider tags whose commit is reachable from HEAD
+    #[arg(long, global = true)]
+    reachable_only: bool,
+    /// Only follow the first parent of merge commits when checking reachability
+    #[arg(long, requires = "reachable_only", global = true)]
+    first_parent: bool,
+    /// How to order tags with exactly the same version
+    #[arg(long, value_enum, default_value_t = TieBreak::Date)]
+    tie_break: TieBreak,
+    /// Cache the commit and commit time of each tag in this JSON file, so later runs only
+    /// resolve new or moved tags (used by the date tie-break, `--check-monotonic`,
+    /// `--reachable-only`, `--require-resolvable` and `--within-days`)
+    #[arg(long, value_name = "PATH")]
+    cache_file: Option<PathBuf>,
+    /// Whether the latest tag is the one with the highest version or the most recently created
+    #[arg(long, value_enum, default_value_t = SortBy::Version)]
+    sort_by: SortBy,
+    /// How the tags are versioned; with `none`, any tag with the prefix matches and
+    /// `--sort-by date` must be given
+    #[arg(long, value_enum, default_value_t = Scheme::Semver, global = true)]
+    scheme: Scheme,
+    /// Use this tag as the latest tag when no tags match instead of failing (e.g. v0.0.0)
+    #[arg(long, value_name = "TAG")]
+    default_tag: Option<String>,
+    /// The separator between the major, minor and patch components (e.g. "_" for v1_2_3)
+    #[arg(long, default_value = ".", global = true)]
+    component_separator: String,
+    /// In prerelease mode, fail if any tag uses a prerelease identifier outside this list
+    #[arg(long, value_delimiter = ',')]
+    allowed_prereleases: Vec<String>,
+    /// Fail unless the commit of the selected tag is in the history of the release branch
+    #[arg(long)]
+    require_on_release_branch: bool,
+    /// Output the next prerelease tag, incrementing the counter of the latest prerelease
+    #[arg(long)]
+    next_prerelease: bool,
+    /// The core version `--next-prerelease` creates a prerelease of (defaults to the version
+    /// of the latest prerelease)
+    #[arg(long, value_name = "VERSION", requires = "next_prerelease")]
+    target_version: Option<Version>,
+    /// Output the number of prerelease tags with the core version of the selected tag, or of
+    /// the next prerelease with `--next-prerelease` (e.g. 3 for v1.3.0-rc.1 to v1.3.0-rc.3)
+    #[arg(long)]
+    count_prerelease_iterations: bool,
+    /// Fail if several tags have exactly the latest version and the tie-break cannot order them
+    #[arg(long)]
+    fail_on_tie: bool,
+    /// Output the distinct authors of the commits since the latest tag
+    #[arg(long)]
+    contributors: bool,
+    /// Write the next tag, the latest tag it follows, the bump and the commits since the latest
+    /// tag to this JSON file for release note generators
+    #[arg(long, value_name = "PATH")]
+    release_metadata: Option<PathBuf>,
+    /// Output the commits since the latest tag, one `<short-sha> <subject>` per line, newest
+    /// first (at most `--max-scan-commits` of them)
+    #[arg(long)]
+    commit_list: bool,
+    /// Output the URL of `--remote` and the `owner/name` slug of the repository, falling back to
+    /// GITHUB_REPOSITORY for the slug
+    #[arg(long)]
+    emit_remote: bool,
+    /// Also report tag policy violations as GitHub actions workflow annotations
+    #[arg(long)]
+    annotations: bool,
+    /// Output the next tag, bumping this component of the latest tag
+    #[arg(long, value_enum)]
+    bump: Option<Bump>,
+    /// With `--bump auto`, bump the major version for breaking changes and the minor version
+    /// for features even while the major version is 0
+    #[arg(long)]
+    strict_semver_bump: bool,
+    /// Scan at most this many commits for conventional commits (since the latest tag, or the
+    /// latest stable tag for `--breaking-since-stable`), warning if there are more
+    #[arg(long, value_name = "N")]
+    max_scan_commits: Option<usize>,
+    /// Leave merge commits out of the conventional commit scans, as their messages are often
+    /// not conventional
+    #[arg(long)]
+    ignore_merges: bool,
+    /// Output the bump the conventional commits in this range call for (e.g. "v1.2.0..HEAD"),
+    /// which `--bump auto` then uses instead of the commits since the latest tag
+    #[arg(long, value_name = "FROM..TO")]
+    range_bump: Option<String>,
+    /// Output a nightly tag: the latest tag bumped (the patch unless `--bump` is given) with the
+    /// UTC date as its prerelease (e.g. v1.2.4-nightly.20240312)
+    #[arg(long)]
+    nightly: bool,
+    /// Output the tag to create next: the bumped latest tag on the release branch, or the next
+    /// prerelease on a prerelease branch (bumping the patch unless `--bump` is given)
+    #[arg(long)]
+    recommended_tag: bool,
+    /// Output the stable release of the latest tag as the next tag if it is a prerelease,
+    /// otherwise bump it (the patch unless `--bump` is given)
+    #[arg(long)]
+    finalize: bool,
+    /// Create the next tag as an annotated tag on HEAD (bumping the patch unless `--bump` or
+    /// `--finalize` is given)
+    #[arg(long)]
+    create_tag: bool,
+    /// Print the tag `--create-tag` would create, without creating it
+    #[arg(long, conflicts_with = "create_tag")]
+    preview_tag: bool,
+    /// Push the tag `--create-tag` creates to the remote
+    #[arg(long, requires = "create_tag")]
+    push: bool,
+    /// Create and push tags without asking for confirmation when not running in CI
+    #[arg(long)]
+    yes: bool,
+    /// Branches that always select stable tags, as glob patterns (e.g. "release/*")
+    #[arg(long, value_delimiter = ',', value_parser = Pattern::new)]
+    stable_branches: Vec<Pattern>,
+    /// Branches that always select prerelease tags, as glob patterns (e.g. "feature/*")
+    #[arg(long, value_delimiter = ',', value_parser = Pattern::new)]
+    prerelease_branches: Vec<Pattern>,
+    /// The mode for branches other than the release branch matching neither pattern list
+    /// (defaults to prerelease)
+    #[arg(long, value_enum)]
+    default_branch_mode: Option<BranchMode>,
+    /// The mode when the workflow was triggered by pushing a tag (a detached HEAD with a
+    /// `refs/tags/...` GITHUB_REF), where there is no branch to compare with the release branch
+    #[arg(long, value_enum)]
+    on_tag_mode: Option<OnTagMode>,
+    /// Also output the latest tag across stable and prerelease tags
+    #[arg(long)]
+    include_both: bool,
+    /// Compare the version in this Cargo.toml, package.json or pyproject.toml with the latest tag
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+    /// Decide the mode from the branch a pull request merges into instead of the current branch
+    #[arg(long, value_name = "BRANCH")]
+    target_branch: Option<String>,
+    /// Also output the latest tag on this branch and whether the selected tag is greater
+    #[arg(long, value_name = "BRANCH")]
+    compare_branch: Option<String>,
+    /// The current branch, instead of detecting it from HEAD (e.g. in a bare repository)
+    #[arg(long, value_name = "BRANCH", conflicts_with = "target_branch")]
+    branch: Option<String>,
+    /// Output the latest tag formatted with this template (e.g. "release-{major}_{minor}")
+    #[arg(long, value_name = "TEMPLATE")]
+    template: Option<String>,
+    /// Report tags on newer commits with lower versions than tags on older commits, as a warning
+    /// or an error
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "warn", value_name = "LEVEL")]
+    check_monotonic: Option<CheckLevel>,
+    /// Report gaps in the prerelease counters of each version and channel (e.g. rc.1 then rc.3),
+    /// as a warning or an error
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "warn", value_name = "LEVEL")]
+    check_sequential_prereleases: Option<CheckLevel>,
+    /// Write an empty latest tag and succeed when no tags match instead of failing
+    #[arg(long, conflicts_with = "default_tag")]
+    quiet_no_match: bool,
+    /// Only consider annotated tags carrying a GPG or SSH signature
+    #[arg(long)]
+    signed_only: bool,
+    /// Only consider tags whose commit is in the local repository, skipping dangling tags
+    #[arg(long, global = true)]
+    require_resolvable: bool,
+    /// Only consider tags whose commit was made in the last N days
+    #[arg(long, value_name = "N")]
+    within_days: Option<u64>,
+    /// Only consider tags on commits authored by these people, given by email or name (e.g.
+    /// "alice@example.com,Bob Smith")
+    #[arg(long, value_delimiter = ',', value_name = "AUTHORS")]
+    author_allowlist: Vec<String>,
+    /// Only consider tags with a published (non-draft) GitHub release, found through the API
+    /// of the repository in GITHUB_REPOSITORY
+    #[cfg(feature = "github-api")]
+    #[arg(long)]
+    published_only: bool,
+    /// Leave the tags pointing at HEAD out of the previous tags, which are the highest tags below
+    /// the latest ones, e.g. for a changelog of a tagged HEAD
+    #[arg(long)]
+    exclude_head_tag: bool,
+    /// Fail if the selected tag is a lightweight tag rather than an annotated one
+    #[arg(long)]
+    require_annotated: bool,
+    /// Fail unless the annotation message of the selected tag names the version of the tag
+    #[arg(long)]
+    verify_message_version: bool,
+    /// Fail if any matching prerelease tag is ranked above its own stable release, as a check
+    /// of the tag ordering
+    #[arg(long)]
+    validate_ordering: bool,
+    /// Fail if any matching stable tag has no prerelease tag of the same version (e.g. v1.0.0
+    /// without v1.0.0-rc.1), for release processes with a mandatory release candidate
+    #[arg(long)]
+    require_rc_before_stable: bool,
+    /// Verify the signature of the selected tag against the public keys in this file
+    #[cfg(feature = "verify-key")]
+    #[arg(long, value_name = "PATH")]
+    verify_key: Option<PathBuf>,
+    /// Output who signed the selected tag: the email of a known GPG key, otherwise the GPG key
+    /// id or SSH key fingerprint (empty for unsigned tags)
+    #[cfg(feature = "signer-output")]
+    #[arg(long)]
+    emit_signer: bool,
+    /// In prerelease mode, match prereleases with any identifier instead of only the suffix
+    #[arg(long)]
+    any_prerelease: bool,
+    /// Among prereleases of the same core version, prefer these channels in this order over
+    /// semver ordering (e.g. "rc,beta")
+    #[arg(long, value_delimiter = ',')]
+    prefer_channel: Vec<String>,
+    /// Whether to select the highest or the lowest matching version as the latest tag
+    #[arg(long, value_enum, default_value_t = Selection::Max)]
+    select: Selection,
+    /// Skip tags whose version is listed in this file, one version per line
+    #[arg(long, value_name = "PATH")]
+    yanked_file: Option<PathBuf>,
+    /// Only consider tags whose build metadata contains this text (e.g. "linux")
+    #[arg(long, value_name = "TEXT")]
+    metadata_contains: Option<String>,
+    /// Also exclude tags with build metadata (e.g. v1.0.0+rc) when selecting stable tags
+    #[arg(long)]
+    stable_requires_no_metadata: bool,
+    /// Only consider tags whose version satisfies every version requirement in this file, one
+    /// per line (e.g. ">=1.2" or "!=1.5.0")
+    #[arg(long, value_name = "PATH")]
+    constraints: Option<PathBuf>,
+    /// Only consider tags whose version satisfies this range (e.g. "^1.2.0" in npm syntax)
+    #[arg(long, value_name = "RANGE")]
+    range: Option<String>,
+    /// The syntax `--range` is written in
+    #[arg(long, value_enum, default_value_t = RangeSyntax::Cargo, requires = "range")]
+    range_syntax: RangeSyntax,
+    /// When to color the log; the output file is never colored
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+}
+
+/// Reports that can be produced instead of selecting the latest tag
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the prerelease channels used by the tags, with the number of tags in each
+    Channels,
+    /// Report tags that look like versions but do not follow the tag pattern, failing if any do
+    Lint,
+    /// Find the latest stable tag in each of a list of repositories
+    Batch {
+        /// A file listing the repository paths, one per line, relative to the file
+        #[arg(long, value_name = "PATH")]
+        repos_file: PathBuf,
+    },
+    /// Compare two versions, printing -1, 0 or 1 as the first is lower than, equal to or greater
+    /// than the second
+    Compare {
+        /// The first version, with or without the tag prefix (e.g. v1.2.0)
+        #[arg(value_name = "A")]
+        first: String,
+        /// The second version, with or without the tag prefix
+        #[arg(value_name = "B")]
+        second: String,
+    },
+}
+
+/// Whether a branch selects stable or prerelease tags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BranchMode {
+    Stable,
+    Prerelease,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let github_output_path =
-        env::var("GITHUB_OUTPUT").map_err(|_| "GITHUB_OUTPUT environment variable missing.")?;
-    let args = Args::parse();
-    let working_directory = env::current_dir()?;
+/// Whether a tag-triggered workflow selects stable or prerelease tags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OnTagMode {
+    /// Select prerelease tags if the triggering tag is a prerelease
+    Infer,
+    Stable,
+    Prerelease,
+}
+
+/// Patterns deciding which branches select stable or prerelease tags
+#[derive(Debug, Default)]
+struct BranchRules {
+    /// Branches matching any of these select stable tags
+    stable: Vec<Pattern>,
+    /// Branches matching any of these select prerelease tags
+    prerelease: Vec<Pattern>,
+    /// The mode for branches other than the release branch matching neither list, or `None`
+    /// for prerelease
+    default_mode: Option<BranchMode>,
+}
 
-    let repository = Repository::discover(working_directory.as_path())
-        .map_err(|_| "No git repository found in working directory or parent directories")?;
+/// How to report a failed check
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CheckLevel {
+    /// Print a warning and continue
+    Warn,
+    /// Fail the run
+    Error,
+}
 
-    let branch_name = repository
-        .head()?
-        .shorthand()
-        .ok_or("Failed to get current branch name")?
-        .to_string();
+/// The places tags can be read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TagSource {
+    /// Tags in the local repository
+    Local,
+    /// Tags advertised by the remote, queried without fetching
+    Remote,
+    /// Version strings stored in git notes attached to commits
+    Notes,
+}
 
-    let prerelease = branch_name != args.release_branch;
+/// How to order the matching tags to find the latest one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// The tag with the highest version
+    Version,
+    /// The most recently created tag, by the tagger time of annotated tags and the commit time
+    /// of lightweight tags
+    Date,
+}
 
-    if prerelease {
+/// How the versions of the tags are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Scheme {
+    /// Semantic versions after the prefix
+    Semver,
+    /// No version semantics at all, e.g. for tags that are re-created rather than versioned
+    None,
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let mut args = Args::parse();
+    match args.color {
+        ColorChoice::Auto => anstream::ColorChoice::Auto,
+        ColorChoice::Always => anstream::ColorChoice::Always,
+        ColorChoice::Never => anstream::ColorChoice::Never,
+    }
+    .write_global();
+
+    // Comparing versions does not look at any repository, and is only written to an output
+    // file if one is available, so it also works outside of GitHub actions
+    if let Some(Command::Compare { first, second }) = &args.command {
+        if args.scheme == Scheme::None {
+            return Err("--scheme none has no versions to compare".into());
+        }
+        let filter = TagFilter {
+            tag_prefixes: args.tag_prefix,
+            component_separator: args.component_separator,
+            ..Default::default()
+        };
+        let comparison = compare_versions(first, second, &filter)? as i8;
+        println!("{comparison}");
+        let github_output = env::var_os("GITHUB_OUTPUT").map(PathBuf::from);
+        if github_output.is_some() || !args.output_file.is_empty() {
+            let output_targets =
+                get_output_targets(&args.format, &args.output_file, github_output)?;
+            let mut outputs = Outputs::new().with_prefix(&args.output_prefix);
+            outputs.insert("comparison", comparison);
+            outputs.write_all(&output_targets)?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let output_targets = get_output_targets(
+        &args.format,
+        &args.output_file,
+        env::var_os("GITHUB_OUTPUT").map(PathBuf::from),
+    )?;
+    let mut outputs = Outputs::new().with_prefix(&args.output_prefix);
+
+    // Each repository in a batch is opened separately, so the current one does not matter
+    if let Some(Command::Batch { repos_file }) = &args.command {
+        let filter = TagFilter {
+            tag_prefixes: args.tag_prefix,
+            component_separator: args.component_separator,
+            ..Default::default()
+        };
+        let results = run_batch(repos_file, &args.ref_namespace, &filter)?;
+        println!("Batch results: {}", Value::Array(results.clone()));
+        outputs.insert("results", results);
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let search_root = match args.repo_path {
+        Some(repo_path) => repo_path,
+        None => env::current_dir()
+            .map_err(|e| format!("Failed to access the current working directory: {e}"))?,
+    };
+
+    let repository = Repository::discover(&search_root).map_err(|_| {
+        format!(
+            "No git repository found in {} or parent directories",
+            search_root.display()
+        )
+    })?;
+
+    // A shallow clone may be missing the commits and tags of older releases
+    if repository.is_shallow() {
+        let message = "The repository is a shallow clone, so older tags may be missing and the latest tag may be outdated. Check out with fetch-depth: 0 or run git fetch --tags --unshallow first.";
+        if args.annotations {
+            println!("{}", format_annotation("warning", message));
+        } else {
+            println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+        }
+        warnings::record("shallow_clone", None, message);
+    }
+
+    // Reports produced by a subcommand do not depend on the branch
+    let release_branch = match args.command {
+        Some(_) => None,
+        None => Some(
+            args.release_branch
+                .as_deref()
+                .ok_or("--release-branch is required")?,
+        ),
+    };
+    let branch_rules = BranchRules {
+        stable: args.stable_branches,
+        prerelease: args.prerelease_branches,
+        default_mode: args.default_branch_mode,
+    };
+    let triggering_tag = match (release_branch, &args.target_branch, &args.branch) {
+        (Some(_), None, None) if args.on_tag_mode.is_some() && repository.head_detached()? => {
+            get_triggering_tag(|name| env::var(name).ok())
+        }
+        _ => None,
+    };
+    let current_branch = match (release_branch, &args.target_branch) {
+        (Some(_), None) if triggering_tag.is_some() => None,
+        (Some(_), None) => Some(get_current_branch(&repository, args.branch.as_deref())?),
+        (Some(_), Some(_)) if args.suffix_from_branch => {
+            Some(get_current_branch(&repository, None)?)
+        }
+        _ => None,
+    };
+    let prerelease = match (release_branch, &args.target_branch, &current_branch) {
+        (Some(_), None, None) if let Some(triggering_tag) = &triggering_tag => {
+            // The prefix of the other tags is not known yet, but the triggering tag shows its own
+            let tag_prefixes = if args.auto_prefix {
+                detect_tag_prefix(slice::from_ref(triggering_tag), &args.component_separator)
+                    .into_iter()
+                    .collect()
+            } else {
+                args.tag_prefix.clone()
+            };
+            let filter = TagFilter {
+                tag_prefixes,
+                component_separator: args.component_separator.clone(),
+                ..Default::default()
+            };
+            is_prerelease_trigger(
+                triggering_tag,
+                args.on_tag_mode.unwrap_or(OnTagMode::Infer),
+                &filter,
+            )
+        }
+        (Some(release_branch), Some(target_branch), _) => {
+            is_prerelease_target(&repository, target_branch, release_branch, &args.remote)?
+        }
+        (Some(release_branch), None, Some(current_branch)) => {
+            is_prerelease_branch(current_branch, release_branch, &branch_rules)
+        }
+        _ => false,
+    };
+    let prerelease_suffix = match &current_branch {
+        Some(current_branch) if args.suffix_from_branch && prerelease => {
+            match get_branch_suffix(current_branch) {
+                Some(suffix) => {
+                    println!("Using the prerelease suffix {suffix} derived from the branch name.");
+                    suffix
+                }
+                None => {
+                    let message = format!(
+                        "No prerelease suffix can be derived from the branch name {current_branch}. Using {} instead.",
+                        args.prerelease_suffix
+                    );
+                    println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+                    warnings::record("branch_suffix", None, &message);
+                    args.prerelease_suffix.clone()
+                }
+            }
+        }
+        _ => args.prerelease_suffix.clone(),
+    };
+
+    // The mode is only known now, so an empty suffix is reported as a usage error here
+    if prerelease && !args.any_prerelease && prerelease_suffix.is_empty() {
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                "--prerelease-suffix must not be empty when selecting prerelease tags, unless --any-prerelease is given",
+            )
+            .exit();
+    }
+
+    let tag_names = match args.source {
+        TagSource::Local => get_local_tag_names(&repository, &args.ref_namespace)?,
+        TagSource::Remote => get_remote_tag_names(&repository, &args.ref_namespace, &args.remote)?,
+        TagSource::Notes => get_note_versions(&repository, &args.notes_ref)?,
+    };
+
+    if args.auto_prefix {
+        let tag_prefix = match detect_tag_prefix(&tag_names, &args.component_separator) {
+            Some(tag_prefix) => {
+                println!("Detected the tag prefix {tag_prefix:?}.");
+                tag_prefix
+            }
+            None => {
+                let message =
+                    "No tag prefix is shared by all the version tags. Using no prefix instead.";
+                println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+                warnings::record("auto_prefix", None, message);
+                String::new()
+            }
+        };
+        args.tag_prefix = vec![tag_prefix];
+    }
+
+    let mut tags = tag_names.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let needs_tag_commits = args.reachable_only
+        || args.require_resolvable
+        || args.within_days.is_some()
+        || args.tie_break == TieBreak::Date
+        || args.check_monotonic.is_some();
+    let tag_commits = match &args.cache_file {
+        Some(_) if !needs_tag_commits => {
+            let message =
+                "No option resolves the commits of tags, so --cache-file has nothing to speed up.";
+            println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+            warnings::record("cache_file", None, message);
+            None
+        }
+        Some(cache_file) => {
+            let mut cache = TagCache::load(cache_file)?;
+            let tag_commits = cache.resolve_tags(&repository, &args.ref_namespace, &tags);
+            cache.save()?;
+            println!(
+                "Resolved {} of {} tags, the rest were cached.",
+                cache.resolved(),
+                tags.len()
+            );
+            Some(tag_commits)
+        }
+        None => None,
+    };
+
+    if args.reachable_only {
+        let reachable = get_reachable_commits(&repository, args.first_parent)?;
+        tags.retain(|tag| {
+            get_tag_commit(&repository, &args.ref_namespace, tag, tag_commits.as_ref())
+                .is_some_and(|(commit, _)| reachable.contains(&commit))
+        });
+    }
+
+    if args.signed_only {
+        tags.retain(|tag| is_signed_tag(&repository, &args.ref_namespace, tag));
+    }
+
+    if args.require_resolvable {
+        tags.retain(|tag| {
+            let resolvable =
+                get_tag_commit(&repository, &args.ref_namespace, tag, tag_commits.as_ref())
+                    .is_some();
+            if !resolvable {
+                skip_tag(tag, "its commit is not in the repository");
+            }
+            resolvable
+        });
+    }
+
+    if let Some(days) = args.within_days {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        tags.retain(|tag| {
+            let recent = get_tag_age_days(
+                &repository,
+                &args.ref_namespace,
+                tag,
+                tag_commits.as_ref(),
+                now,
+            )
+            .is_some_and(|age| age < days);
+            if !recent {
+                skip_tag(tag, &format!("its commit is not from the last {days} days"));
+            }
+            recent
+        });
+    }
+
+    if !args.author_allowlist.is_empty() {
+        tags.retain(|tag| {
+            let allowed = is_authored_by(
+                &repository,
+                &args.ref_namespace,
+                tag,
+                &args.author_allowlist,
+            );
+            if !allowed {
+                skip_tag(tag, "its commit is not by an allowed author");
+            }
+            allowed
+        });
+    }
+
+    #[cfg(feature = "github-api")]
+    if args.published_only {
+        let published = github::get_published_tags(
+            &github::CurlApi::from_env(),
+            &github::get_repository_name()?,
+        )?;
+        tags.retain(|tag| published.contains(*tag));
+    }
+
+    if let Some(Command::Channels) = args.command {
+        let filter = TagFilter {
+            tag_prefixes: args.tag_prefix,
+            component_separator: args.component_separator,
+            ..Default::default()
+        };
+        let prerelease_channels = count_prerelease_channels(&tags, &filter);
+        if prerelease_channels.is_empty() {
+            println!("No prerelease channels found.");
+        }
+        for (channel, count) in &prerelease_channels {
+            println!("{channel}: {count}");
+        }
+        outputs.insert(
+            "prerelease_channels",
+            prerelease_channels
+                .into_iter()
+                .map(|(channel, count)| (channel, Value::from(count)))
+                .collect::<Map<_, _>>(),
+        );
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Lint) = args.command {
+        let filter = TagFilter {
+            tag_prefixes: args.tag_prefix,
+            component_separator: args.component_separator,
+            ..Default::default()
+        };
+        let violations = find_nonconforming_tags(&tags, &filter)?;
+        outputs.insert("lint_violations", violations.clone());
+        outputs.write_all(&output_targets)?;
+        if !violations.is_empty() {
+            return Err(format!(
+                "Tags do not follow the tag pattern: {}",
+                violations.join(", ")
+            )
+            .into());
+        }
+        println!("All version tags follow the tag pattern.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(proposed_tag) = &args.check {
+        // A tag the filters left out still exists, and creating it again would fail
+        let exists = tag_names.contains(proposed_tag);
+        outputs.insert("tag_exists", exists);
+        outputs.write_all(&output_targets)?;
+        if exists {
+            eprintln!("Tag {proposed_tag} already exists.");
+            return Ok(ExitCode::from(TAG_EXISTS_EXIT_CODE));
+        }
+        println!("Tag {proposed_tag} does not exist yet.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(feature = "github-api")]
+    let topic_channels = match args.channels_from {
+        Some(github::ChannelSource::Github) => {
+            let topic_channels = github::get_topic_channels(
+                &github::CurlApi::from_env(),
+                &github::get_repository_name()?,
+            )?;
+            println!(
+                "Channels from the repository topics: {}",
+                topic_channels.join(", ")
+            );
+            topic_channels
+        }
+        None => Vec::new(),
+    };
+    #[cfg(not(feature = "github-api"))]
+    let topic_channels = Vec::new();
+
+    let channels = get_channels(args.channels, topic_channels, &prerelease_suffix);
+    let commit_times = if args.tie_break != TieBreak::Date && args.check_monotonic.is_none() {
+        HashMap::new()
+    } else if let Some(tag_commits) = &tag_commits {
+        tags.iter()
+            .filter_map(|tag| Some((tag.to_string(), tag_commits.get(*tag)?.1)))
+            .collect()
+    } else {
+        get_tag_commit_times(&repository, &args.ref_namespace, &tags)
+    };
+    let filter = TagFilter {
+        tag_prefixes: args.tag_prefix,
+        prerelease_suffix,
+        prerelease,
+        max_version: args.max_version,
+        major: args.major,
+        tie_break: args.tie_break,
+        commit_times: if args.tie_break == TieBreak::Date {
+            commit_times.clone()
+        } else {
+            HashMap::new()
+        },
+        component_separator: args.component_separator,
+        any_prerelease: args.any_prerelease,
+        prefer_channels: args.prefer_channel,
+        yanked: match &args.yanked_file {
+            Some(yanked_file) => read_yanked_versions(yanked_file)?,
+            None => Vec::new(),
+        },
+        metadata_contains: args.metadata_contains,
+        stable_requires_no_metadata: args.stable_requires_no_metadata,
+        constraints: {
+            let mut constraints = match &args.constraints {
+                Some(constraints) => read_constraints(constraints)?,
+                None => Vec::new(),
+            };
+            if let Some(range) = &args.range {
+                constraints.extend(parse_range(range, args.range_syntax)?);
+            }
+            constraints
+        },
+    };
+
+    if filter.prerelease && !args.allowed_prereleases.is_empty() {
+        check_allowed_prereleases(&tags, &filter, &args.allowed_prereleases)
+            .map_err(|e| policy_error(e.to_string(), args.annotations))?;
+    }
+
+    if args.validate_ordering {
+        let violations = find_ordering_violations(&tags, &filter, compare_tags)?;
+        if !violations.is_empty() {
+            return Err(format!(
+                "Tag ordering is broken, prereleases are ranked above their stable release: {}",
+                violations.join(", ")
+            )
+            .into());
+        }
+        println!("No prerelease is ranked above its stable release.");
+    }
+
+    if args.require_rc_before_stable {
+        let without_prerelease = find_stable_tags_without_prerelease(&tags, &filter)?;
+        if !without_prerelease.is_empty() {
+            return Err(policy_error(
+                format!(
+                    "Stable tags were made without a prerelease of the same version: {}",
+                    without_prerelease.join(", ")
+                ),
+                args.annotations,
+            ));
+        }
+        println!("Every stable tag has a prerelease of the same version.");
+    }
+
+    if args.group_by_channel && !args.list && !args.all_channels {
+        return Err("--group-by-channel needs --list or --all-channels".into());
+    }
+    let tags_by_channel = if args.group_by_channel {
+        Some(Value::Object(group_tags_by_channel(&tags, &filter)?))
+    } else {
+        None
+    };
+
+    if args.list {
+        let listed_tags = get_matching_tags(&tags, &filter)?
+            .into_iter()
+            .map(|tag| match parse_tag_version(tag, &filter) {
+                Some(version) if args.emit_sort_key => format!("{tag}\t{version}"),
+                _ => tag.to_string(),
+            })
+            .collect::<Vec<_>>();
+        println!("Matching tags:");
+        for tag in &listed_tags {
+            println!("{tag}");
+        }
+        outputs.insert("tags", listed_tags);
+        if let Some(tags_by_channel) = tags_by_channel {
+            println!("Tags by channel: {tags_by_channel}");
+            outputs.insert("tags_by_channel", tags_by_channel);
+        }
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(pattern) = &args.per_branch {
+        let stable_filter = TagFilter {
+            prerelease: false,
+            ..filter.clone()
+        };
+        let per_branch = Value::Object(get_branch_latest_tags(
+            &repository,
+            &args.ref_namespace,
+            &tags,
+            pattern,
+            &args.remote,
+            &stable_filter,
+        )?);
+        println!("Latest tags by branch: {per_branch}");
+        outputs.insert("per_branch", per_branch);
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.all_channels {
+        let all_channels = Value::Object(get_all_channel_tags(&tags, &filter, &channels)?);
+        println!("Latest tags by channel: {all_channels}");
+        outputs.insert("all_channels", all_channels);
+        if let Some(tags_by_channel) = tags_by_channel {
+            println!("Tags by channel: {tags_by_channel}");
+            outputs.insert("tags_by_channel", tags_by_channel);
+        }
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.scheme == Scheme::None && args.sort_by != SortBy::Date {
+        return Err("--scheme none has no versions to order, so it needs --sort-by date".into());
+    }
+
+    if args.sort_by == SortBy::Date {
+        let candidates = match args.scheme {
+            Scheme::Semver => get_matching_tags(&tags, &filter)?,
+            Scheme::None => tags
+                .iter()
+                .copied()
+                .filter(|tag| split_tag_prefix(tag, &filter).is_some())
+                .collect(),
+        };
+        let latest_tag = find_newest_tag(&repository, &args.ref_namespace, &candidates, &filter)
+            .ok_or_else(|| match args.scheme {
+                Scheme::Semver => no_matching_tags_error(&filter),
+                Scheme::None => format!(
+                    "No tags found with the prefix {}",
+                    filter.tag_prefixes.join(" or ")
+                )
+                .into(),
+            })?;
+        println!("Most recently created tag: {TAG_STYLE}{latest_tag}{TAG_STYLE:#}");
+        outputs.insert(
+            "latest_tag_ref",
+            format!("{}/{latest_tag}", args.ref_namespace),
+        );
+        outputs.insert(
+            "latest_tag_sha",
+            get_tag_sha(
+                &repository,
+                &args.ref_namespace,
+                &latest_tag,
+                args.short_sha,
+                args.abbrev,
+            ),
+        );
+        outputs.insert("latest_tag", latest_tag);
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.next_prerelease {
+        let next_prerelease_tag =
+            get_next_prerelease_tag(&tags, &filter, args.target_version.as_ref())?;
+        println!("Next prerelease tag: {next_prerelease_tag}");
+        if args.count_prerelease_iterations
+            && let Some(version) = parse_tag_version(&next_prerelease_tag, &filter)
+        {
+            outputs.insert(
+                "prerelease_iterations",
+                count_prerelease_iterations(&tags, &filter, &version)?,
+            );
+        }
+        outputs.insert("next_prerelease_tag", next_prerelease_tag);
+        outputs.write_all(&output_targets)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(level) = args.check_monotonic {
+        let regressions = find_version_regressions(&tags, &filter, &commit_times)?;
+        if level == CheckLevel::Error && !regressions.is_empty() {
+            let regressions = regressions
+                .iter()
+                .map(|(tag, previous)| format!("{tag} after {previous}"))
+                .collect::<Vec<_>>();
+            return Err(policy_error(
+                format!(
+                    "Versions go backwards over time: {}",
+                    regressions.join(", ")
+                ),
+                args.annotations,
+            ));
+        }
+        for (tag, previous) in &regressions {
+            let message =
+                format!("{tag} is on a newer commit than {previous} but has a lower version.");
+            if args.annotations {
+                println!("{}", format_annotation("warning", &message));
+            } else {
+                println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+            }
+            warnings::record("version_regression", Some(tag), &message);
+        }
+    }
+
+    if let Some(level) = args.check_sequential_prereleases {
+        let missing = find_missing_prereleases(&tags, &filter);
+        if level == CheckLevel::Error && !missing.is_empty() {
+            return Err(policy_error(
+                format!(
+                    "Prerelease counters skip versions, missing: {}",
+                    missing.join(", ")
+                ),
+                args.annotations,
+            ));
+        }
+        for tag in &missing {
+            let message = format!("{tag} is missing from its prerelease series.");
+            if args.annotations {
+                println!("{}", format_annotation("warning", &message));
+            } else {
+                println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+            }
+            warnings::record("missing_prerelease", Some(tag), &message);
+        }
+    }
+
+    if args.fail_on_tie {
+        check_unresolved_tie(&tags, &filter)
+            .map_err(|e| policy_error(e.to_string(), args.annotations))?;
+    }
+
+    let latest_tag = match &args.default_tag {
+        Some(default_tag) => find_selected_tag(&tags, &filter, args.select)?.unwrap_or_else(|| {
+            let message = format!("No matching tags found. Using the default tag ({default_tag}).");
+            println!("{message}");
+            warnings::record("default_tag", Some(default_tag), &message);
+            default_tag.clone()
+        }),
+        None if args.quiet_no_match => match find_selected_tag(&tags, &filter, args.select)? {
+            Some(latest_tag) => latest_tag,
+            None => {
+                println!("No matching tags found.");
+                outputs.insert("latest_tag", "");
+                outputs.write_all(&output_targets)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+        },
+        None => get_selected_tag(&tags, &filter, args.select)?,
+    };
+
+    println!("Latest tag found: {TAG_STYLE}{latest_tag}{TAG_STYLE:#}");
+
+    // A default tag that does not exist has no commit to check
+    if args.require_on_release_branch
+        && let Some(release_branch) = release_branch
+        && tags.contains(&latest_tag.as_str())
+    {
+        check_tag_on_branch(
+            &repository,
+            &args.ref_namespace,
+            &latest_tag,
+            release_branch,
+            &args.remote,
+        )?;
+    }
+
+    if args.require_annotated && tags.contains(&latest_tag.as_str()) {
+        check_annotated_tag(&repository, &args.ref_namespace, &latest_tag)?;
+    }
+
+    if args.verify_message_version && tags.contains(&latest_tag.as_str()) {
+        check_message_version(&repository, &args.ref_namespace, &latest_tag, &filter)?;
+        println!("The message of {latest_tag} names its version.");
+    }
+
+    #[cfg(feature = "verify-key")]
+    if let Some(verify_key) = &args.verify_key {
+        verify_tag(&repository, &args.ref_namespace, &latest_tag, verify_key)?;
+        println!("Verified the signature of {latest_tag}.");
+    }
+
+    #[cfg(feature = "signer-output")]
+    if args.emit_signer {
+        let signer = signature::get_signer(
+            &repository,
+            &args.ref_namespace,
+            &latest_tag,
+            args.verify_key.as_deref(),
+        )?;
         println!(
-            "Current branch ({branch_name}) is not the release branch ({release_branch}). Including only prerelease tags.",
-            release_branch = args.release_branch
+            "Signer of {latest_tag}: {}",
+            signer.as_deref().unwrap_or("none")
         );
+        outputs.insert("signer", signer);
+    }
+
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    let prerelease_filter = TagFilter {
+        prerelease: true,
+        ..filter.clone()
+    };
+    let latest_stable_tag = find_latest_tag(&tags, &stable_filter)?.unwrap_or_default();
+    // For a changelog of HEAD, the previous release is the latest one that is not HEAD's own tag
+    let (previous_stable, previous_prerelease) = get_previous_tags(
+        &repository,
+        &args.ref_namespace,
+        &tags,
+        &filter,
+        args.exclude_head_tag,
+    )?;
+    let previous_tag = if filter.prerelease {
+        previous_prerelease.clone()
     } else {
+        previous_stable.clone()
+    };
+
+    outputs.insert(
+        "latest_tag",
+        match (
+            &args.output_prefix_override,
+            split_tag_prefix(&latest_tag, &filter),
+        ) {
+            (Some(prefix), Some((_, version))) => format!("{prefix}{version}"),
+            _ => latest_tag.clone(),
+        },
+    );
+    // Versions read from notes and a default tag that does not exist have no ref
+    outputs.insert(
+        "latest_tag_ref",
+        if args.source != TagSource::Notes && tags.contains(&latest_tag.as_str()) {
+            format!("{}/{latest_tag}", args.ref_namespace)
+        } else {
+            String::new()
+        },
+    );
+    outputs.insert(
+        "latest_tag_canonical",
+        parse_tag_version(&latest_tag, &filter)
+            .map(|version| version.to_string())
+            .unwrap_or_default(),
+    );
+    outputs.insert(
+        "channel",
+        parse_tag_version(&latest_tag, &filter)
+            .map(|version| get_channel(&version))
+            .unwrap_or_default(),
+    );
+    outputs.insert(
+        "release_line",
+        parse_tag_version(&latest_tag, &filter)
+            .map(|version| format!("{}.{}", version.major, version.minor))
+            .unwrap_or_default(),
+    );
+    if args.count_release_lines {
+        let (major_lines, minor_lines) = count_release_lines(&tags, &stable_filter)?;
+        println!("Release lines: {major_lines} major, {minor_lines} minor");
+        outputs.insert("major_lines", major_lines);
+        outputs.insert("minor_lines", minor_lines);
+    }
+    if args.count_prerelease_iterations
+        && let Some(version) = parse_tag_version(&latest_tag, &filter)
+    {
+        let iterations = count_prerelease_iterations(&tags, &filter, &version)?;
         println!(
-            "Current branch ({branch_name}) is the release branch. Excluding prerelease tags."
+            "Prerelease iterations of {}.{}.{}: {iterations}",
+            version.major, version.minor, version.patch
+        );
+        outputs.insert("prerelease_iterations", iterations);
+    }
+    outputs.insert("pattern", get_tag_pattern(&filter)?.as_str());
+    if args.suffix_from_branch {
+        outputs.insert("prerelease_suffix", filter.prerelease_suffix.as_str());
+    }
+    outputs.insert(
+        "is_first_release",
+        is_first_release(&tags, &filter, &latest_tag, args.default_tag.as_deref())?,
+    );
+    outputs.insert(
+        "latest_tag_sha",
+        get_tag_sha(
+            &repository,
+            &args.ref_namespace,
+            &latest_tag,
+            args.short_sha,
+            args.abbrev,
+        ),
+    );
+    outputs.insert(
+        "on_tag",
+        is_head_on_tag(&repository, &args.ref_namespace, &latest_tag),
+    );
+    outputs.insert(
+        "clean_release",
+        is_head_on_tag(&repository, &args.ref_namespace, &latest_stable_tag)
+            && is_worktree_clean(&repository),
+    );
+    outputs.insert("latest_stable_tag", latest_stable_tag.as_str());
+    outputs.insert("previous_stable", previous_stable);
+    outputs.insert("previous_prerelease", previous_prerelease);
+    outputs.insert(
+        "version_delta",
+        parse_tag_version(&previous_tag, &filter)
+            .zip(parse_tag_version(&latest_tag, &filter))
+            .map(|(previous, latest)| get_version_delta(&previous, &latest)),
+    );
+    outputs.insert("previous_tag", previous_tag);
+    if args.promotion_check {
+        let latest_prerelease = if filter.prerelease_suffix.is_empty() {
+            None
+        } else {
+            find_latest_tag(&tags, &prerelease_filter)?
+        };
+        let promotable = match &latest_prerelease {
+            Some(latest_prerelease) => {
+                is_promotable(&repository, &args.ref_namespace, latest_prerelease)
+            }
+            None => {
+                println!("No prerelease found to promote.");
+                false
+            }
+        };
+        outputs.insert("promotable", promotable);
+    }
+
+    if args.include_both {
+        outputs.insert(
+            "latest_including_prerelease",
+            find_latest_including_prerelease(&tags, &filter)?.unwrap_or_default(),
+        );
+    }
+
+    if let Some(template) = &args.template {
+        let formatted = render_template(template, &latest_tag, &filter)?;
+        println!("Formatted tag: {formatted}");
+        outputs.insert("formatted", formatted);
+    }
+
+    if let Some(manifest) = &args.manifest {
+        let manifest_version = read_manifest_version(manifest)?;
+        let manifest_ahead = parse_tag_version(&latest_tag, &filter)
+            .is_none_or(|latest| manifest_version.cmp_precedence(&latest).is_gt());
+        println!("Manifest version: {manifest_version}");
+        outputs.insert("manifest_version", manifest_version.to_string());
+        outputs.insert("manifest_ahead", manifest_ahead);
+    }
+
+    let (ahead, behind) = get_ahead_behind(&repository, &args.ref_namespace, &latest_tag).unzip();
+    outputs.insert("ahead", ahead);
+    outputs.insert("behind", behind);
+
+    if let Some(compare_branch) = &args.compare_branch {
+        // The branch selects stable or prerelease tags by the same rules as the current one
+        let compare_filter = TagFilter {
+            prerelease: release_branch.is_some_and(|release_branch| {
+                get_branch_mode(compare_branch, release_branch, &branch_rules).0
+                    == BranchMode::Prerelease
+            }),
+            ..filter.clone()
+        };
+        let all_tags = tag_names.iter().map(String::as_str).collect::<Vec<_>>();
+        let compare_latest_tag = find_branch_latest_tag(
+            &repository,
+            &args.ref_namespace,
+            &all_tags,
+            compare_branch,
+            &args.remote,
+            &compare_filter,
+        )?;
+        // Without a tag on the other branch there is nothing to be ahead of
+        let ahead_of_compare = match &compare_latest_tag {
+            Some(compare_latest_tag) => {
+                println!("Latest tag on {compare_branch}: {compare_latest_tag}");
+                Some(compare_tags(&latest_tag, compare_latest_tag, &filter).is_gt())
+            }
+            None => {
+                let message = format!(
+                    "No tag on {compare_branch} matches the filters, so the latest tag is not compared with it."
+                );
+                println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+                warnings::record("compare_branch", None, &message);
+                None
+            }
+        };
+        outputs.insert("compare_latest_tag", compare_latest_tag);
+        outputs.insert("ahead_of_compare", ahead_of_compare);
+    }
+
+    if args.build_number {
+        let build_number = get_reachable_commits(&repository, false)?.len();
+        println!("Build number: {build_number}");
+        outputs.insert("build_number", build_number);
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    outputs.insert(
+        "latest_tag_age_days",
+        get_tag_age_days(
+            &repository,
+            &args.ref_namespace,
+            &latest_tag,
+            tag_commits.as_ref(),
+            now,
+        ),
+    );
+    outputs.insert(
+        "latest_tag_subject",
+        get_tag_subject(&repository, &args.ref_namespace, &latest_tag),
+    );
+
+    if args.contributors {
+        outputs.insert(
+            "contributors",
+            get_contributors_since(&repository, &args.ref_namespace, &latest_tag),
+        );
+    }
+
+    if args.commit_list {
+        outputs.insert(
+            "commits",
+            get_commit_list_since(
+                &repository,
+                &args.ref_namespace,
+                &latest_tag,
+                args.max_scan_commits,
+            ),
+        );
+    }
+
+    if args.emit_remote {
+        let remote_url = repository
+            .find_remote(&args.remote)
+            .ok()
+            .and_then(|remote| remote.url().map(str::to_string));
+        let repo_slug = remote_url
+            .as_deref()
+            .and_then(parse_repo_slug)
+            .or_else(|| env::var("GITHUB_REPOSITORY").ok());
+        outputs.insert("remote_url", remote_url);
+        outputs.insert("repo_slug", repo_slug);
+    }
+
+    // Walking the history can be slow, so it is only done when an option needs the commits
+    // (with --range-bump, the bump uses the commits in the range instead)
+    let counts =
+        if args.commit_counts || (args.bump == Some(Bump::Auto) && args.range_bump.is_none()) {
+            scan_commit_messages_since(
+                &repository,
+                &args.ref_namespace,
+                &latest_tag,
+                args.max_scan_commits,
+                args.ignore_merges,
+                "conventional commits, so the bump may be too small",
+            )
+            .map(|messages| count_commits(messages.iter().map(String::as_str)))
+        } else {
+            None
+        };
+    if args.commit_counts {
+        outputs.insert("feat_count", counts.as_ref().map(|counts| counts.feat));
+        outputs.insert("fix_count", counts.as_ref().map(|counts| counts.fix));
+        outputs.insert(
+            "breaking_count",
+            counts.as_ref().map(|counts| counts.breaking),
         );
     }
+    if args.breaking_since_stable {
+        outputs.insert(
+            "breaking_since_stable",
+            has_breaking_change_since(
+                &repository,
+                &args.ref_namespace,
+                &latest_stable_tag,
+                args.max_scan_commits,
+                args.ignore_merges,
+            ),
+        );
+    }
+
+    let range_counts = match &args.range_bump {
+        Some(range) => {
+            let messages = get_range_commit_messages(&repository, range, args.ignore_merges)?;
+            let range_counts = count_commits(messages.iter().map(String::as_str));
+            let version = parse_tag_version(&latest_tag, &filter)
+                .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+            let bump = get_commit_bump(&range_counts, &version, args.strict_semver_bump)
+                .to_possible_value()
+                .map(|value| value.get_name().to_string());
+            println!("Bump for {range}: {}", bump.as_deref().unwrap_or_default());
+            outputs.insert("range_bump", bump);
+            Some(range_counts)
+        }
+        None => None,
+    };
+
+    let bump = match (
+        args.bump.unwrap_or_default(),
+        range_counts.as_ref().or(counts.as_ref()),
+    ) {
+        (Bump::Auto, Some(counts)) => {
+            let version = parse_tag_version(&latest_tag, &filter)
+                .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+            get_commit_bump(counts, &version, args.strict_semver_bump)
+        }
+        (Bump::Auto, None) => Bump::Patch,
+        (bump, _) => bump,
+    };
+
+    if args.recommended_tag {
+        let recommended_tag = get_recommended_tag(&tags, &filter, &latest_tag, bump)?;
+        println!("Recommended tag: {recommended_tag}");
+        outputs.insert("recommended_tag", recommended_tag);
+    }
+
+    if args.nightly {
+        let nightly_tag = get_nightly_tag(&latest_tag, &filter, bump, now)
+            .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+        println!("Nightly tag: {nightly_tag}");
+        outputs.insert("nightly_tag", nightly_tag);
+    }
+
+    if args.bump.is_some() || args.finalize || args.create_tag || args.preview_tag {
+        let next_tag = get_next_tag(&latest_tag, &filter, bump, args.finalize)
+            .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+        println!("Next tag: {next_tag}");
+
+        if args.create_tag || args.preview_tag {
+            let new_tag = plan_tag(&repository, &args.ref_namespace, &next_tag)?;
+            if args.preview_tag {
+                println!("Tag that would be created:\n{}", describe_tag(&new_tag));
+            } else {
+                // Tagging by accident is easy to do locally, so ask first unless running in CI
+                if !args.yes && !is_ci(|name| env::var(name).ok()) {
+                    println!("{}", describe_tag(&new_tag));
+                    let question = if args.push {
+                        format!("Create tag {next_tag} and push it to {}?", args.remote)
+                    } else {
+                        format!("Create tag {next_tag}?")
+                    };
+                    if !confirm(&question, &mut io::stdin().lock())? {
+                        return Err("Tag creation cancelled".into());
+                    }
+                }
+                create_tag(&repository, &new_tag)?;
+                println!("Created tag {next_tag} on {}", new_tag.target.id());
+                if args.push {
+                    push_tag(&repository, &args.ref_namespace, &args.remote, &next_tag)?;
+                    println!("Pushed tag {next_tag} to {}", args.remote);
+                }
+            }
+        }
+        outputs.insert("next_tag", next_tag);
+    }
+
+    if let Some(path) = &args.release_metadata {
+        let metadata = get_release_metadata(
+            &repository,
+            &args.ref_namespace,
+            &latest_tag,
+            &filter,
+            bump,
+            args.finalize,
+            args.max_scan_commits,
+        )?;
+        fs::write(path, format!("{metadata:#}\n")).map_err(|e| {
+            format!(
+                "Failed to write the release metadata to {}: {e}",
+                path.display()
+            )
+        })?;
+        println!("Wrote the release metadata to {}", path.display());
+    }
+
+    if args.docker_tags {
+        let docker_tags = get_docker_tags(&latest_tag, &filter)
+            .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+        println!("Docker tags: {}", docker_tags.join(", "));
+        outputs.insert("docker_tags", docker_tags);
+    }
 
-    let tags = repository.tag_names(None)?;
-    let tags = tags.iter().flatten().collect::<Vec<_>>();
-    let latest_tag = get_latest_tag(tags, &args.tag_prefix, &args.prerelease_suffix, prerelease)?;
+    if args.include_message {
+        outputs.insert(
+            "latest_tag_message",
+            get_tag_message(&repository, &args.ref_namespace, &latest_tag),
+        );
+    }
 
-    println!("Latest tag found: {}", latest_tag);
+    if args.warnings_json {
+        outputs.insert("warnings", warnings::take());
+    }
 
     // Write as GitHub actions output
-    write(github_output_path, format!("latest_tag={}\n", latest_tag))?;
+    outputs.write_all(&output_targets)?;
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-/// Generate the appropriate tag pattern based on whether prerelease tags are considered
+/// Check whether the commit of a tag was authored by one of a list of people
 /// # Arguments
-/// * `prerelease` - A boolean indicating if prerelease tags should be included
-/// * `tag_prefix` - The prefix for the tags (e.g., "v")
-/// * `prerelease_suffix` - The suffix for prerelease tags (e.g. beta, rc)
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `authors` - The allowed authors, each an email address (compared case-insensitively) or
+///   a name (compared exactly)
 /// # Returns
-/// A Regex pattern to match the tags
-/// # Errors
-/// Returns an error if the regex pattern is invalid
-fn get_tag_pattern(
-    prerelease: bool,
-    tag_prefix: &str,
-    prerelease_suffix: &str,
-) -> Result<Regex, Box<dyn Error>> {
-    let tag_pattern = if prerelease {
-        Regex::new(&format!(
-            r"^{}\d+.\d+.\d+-{}\.\d+$",
-            tag_prefix, prerelease_suffix
-        ))?
-    } else {
-        Regex::new(&format!(r"^{}\d+.\d+.\d+$", tag_prefix))?
+/// `true` if the author of the tagged commit is in the list, `false` otherwise or if the tag
+/// cannot be resolved locally
+fn is_authored_by(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    authors: &[String],
+) -> bool {
+    let Some(commit) = resolve_tag_commit(repository, ref_namespace, tag)
+        .and_then(|id| repository.find_commit(id).ok())
+    else {
+        return false;
     };
+    let author = commit.author();
+    let name = author.name().unwrap_or_default();
+    let email = author.email().unwrap_or_default();
+    authors
+        .iter()
+        .any(|allowed| allowed == name || allowed.eq_ignore_ascii_case(email))
+}
+
+/// Get the commit a tag points to and its time, from the cache if it is used
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `tag_commits` - The commits of tags read from the cache, if it is used
+/// # Returns
+/// The id of the tagged commit and its time in seconds since the epoch, or `None` if the tag
+/// cannot be resolved locally
+fn get_tag_commit(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    tag_commits: Option<&HashMap<String, (Oid, i64)>>,
+) -> Option<(Oid, i64)> {
+    if let Some(tag_commits) = tag_commits {
+        return tag_commits.get(tag).copied();
+    }
+
+    let commit = repository
+        .find_commit(resolve_tag_commit(repository, ref_namespace, tag)?)
+        .ok()?;
+    Some((commit.id(), commit.time().seconds()))
+}
+
+/// Get the id of the commit a tag points to as text
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `short` - Abbreviate the id to a prefix that is unambiguous in the repository
+/// * `abbrev` - The minimum length of the abbreviation, instead of core.abbrev
+/// # Returns
+/// The full or abbreviated id, or `None` if the tag cannot be resolved locally
+fn get_tag_sha(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    short: bool,
+    abbrev: Option<u8>,
+) -> Option<String> {
+    let commit = repository
+        .find_commit(resolve_tag_commit(repository, ref_namespace, tag)?)
+        .ok()?;
+    let full = commit.id().to_string();
+    if !short {
+        return Some(full);
+    }
+
+    match abbrev {
+        None => commit
+            .as_object()
+            .short_id()
+            .ok()?
+            .as_str()
+            .map(str::to_string),
+        // Lengthen the prefix until it names only this object
+        Some(abbrev) => (usize::from(abbrev)..full.len())
+            .map(|length| &full[..length])
+            .find(|prefix| repository.find_object_by_prefix(prefix, None).is_ok())
+            .or(Some(&full))
+            .map(str::to_string),
+    }
+}
+
+/// Check whether the working tree and index match HEAD
+///
+/// Untracked files are not counted, like `git describe --dirty` does.
+/// # Arguments
+/// * `repository` - The repository to check
+/// # Returns
+/// `true` if no tracked file is modified or staged, `false` otherwise or for a bare repository
+fn is_worktree_clean(repository: &Repository) -> bool {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    repository
+        .statuses(Some(&mut options))
+        .is_ok_and(|statuses| statuses.is_empty())
+}
+
+/// Work out how many whole days ago a tag's commit was made
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `tag_commits` - The commits of tags read from the cache, if it is used
+/// * `now` - The current time in seconds since the epoch
+/// # Returns
+/// The age in days, or `None` if the tag cannot be resolved locally. Commit times are compared
+/// in UTC, so the committer's timezone does not matter, and commits in the future are 0 days old
+fn get_tag_age_days(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    tag_commits: Option<&HashMap<String, (Oid, i64)>>,
+    now: u64,
+) -> Option<u64> {
+    let (_, commit_time) = get_tag_commit(repository, ref_namespace, tag, tag_commits)?;
+    let committed = u64::try_from(commit_time).unwrap_or_default();
+    Some(now.saturating_sub(committed) / (24 * 60 * 60))
+}
+
+/// Get the subject of the commit a tag points to
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// The first line of the commit message, or `None` if the tag cannot be resolved locally. Only
+/// the first line is kept, even when the subject continues on the next line
+fn get_tag_subject(repository: &Repository, ref_namespace: &str, tag: &str) -> Option<String> {
+    let commit = repository
+        .find_commit(resolve_tag_commit(repository, ref_namespace, tag)?)
+        .ok()?;
+    let message = String::from_utf8_lossy(commit.message_bytes()).into_owned();
+    Some(
+        message
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    )
+}
+
+/// Look up the commit time of each tag that can be resolved locally
+/// # Arguments
+/// * `repository` - The repository containing the tags
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - The names of the tags
+/// # Returns
+/// The commit time (in seconds since the epoch) of each resolvable tag
+fn get_tag_commit_times(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+) -> HashMap<String, i64> {
+    tags.iter()
+        .filter_map(|tag| {
+            let commit = repository
+                .find_commit(resolve_tag_commit(repository, ref_namespace, tag)?)
+                .ok()?;
+            Some((tag.to_string(), commit.time().seconds()))
+        })
+        .collect()
+}
+
+/// Get the time a tag was created
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// The tagger time of an annotated tag, or the commit time of a lightweight tag (in seconds
+/// since the epoch), or `None` if the tag cannot be resolved locally
+fn get_tag_creation_time(repository: &Repository, ref_namespace: &str, tag: &str) -> Option<i64> {
+    let reference = repository
+        .find_reference(&format!("{ref_namespace}/{tag}"))
+        .ok()?;
+    if let Some(created) = reference
+        .peel_to_tag()
+        .ok()
+        .and_then(|annotated| Some(annotated.tagger()?.when().seconds()))
+    {
+        return Some(created);
+    }
+    Some(reference.peel_to_commit().ok()?.time().seconds())
+}
 
-    Ok(tag_pattern)
+/// Find the most recently created of some tags, whatever their versions
+///
+/// Tags created at the same time usually point at the same commit (e.g. `v1.2.3` and an alias
+/// such as `latest`), so a tag with a semantic version is preferred over one without, and then
+/// the greatest name, to keep the choice deterministic.
+/// # Arguments
+/// * `repository` - The repository containing the tags
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - The tags to choose from
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The newest tag, or `None` if there are no tags
+fn find_newest_tag(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Option<String> {
+    tags.iter()
+        .max_by_key(|tag| {
+            (
+                get_tag_creation_time(repository, ref_namespace, tag),
+                parse_tag_version(tag, filter).is_some(),
+                **tag,
+            )
+        })
+        .map(|tag| tag.to_string())
 }
 
-/// Get the latest tag from a list of tags based on semantic versioning
+/// Collect every commit reachable from HEAD
 /// # Arguments
-/// * `tags` - A vector of tag strings
-/// * `tag_prefix` - The prefix for the tags (e.g., "v")
-/// * `prerelease_suffix` - The suffix for prerelease tags (e.g. beta, rc)
-/// * `prerelease` - A boolean indicating if prerelease tags should be included
+/// * `repository` - The repository to walk
+/// * `first_parent` - Only follow the first parent of merge commits
 /// # Returns
-/// The latest tag as a string
+/// The ids of the reachable commits
 /// # Errors
-/// Returns an error if no matching tags are found
-fn get_latest_tag(
-    tags: Vec<&str>,
-    tag_prefix: &str,
-    prerelease_suffix: &str,
-    prerelease: bool,
-) -> Result<String, Box<dyn Error>> {
-    let tag_pattern = get_tag_pattern(prerelease, tag_prefix, prerelease_suffix)?;
+/// Returns an error if HEAD cannot be resolved or the history cannot be walked
+fn get_reachable_commits(
+    repository: &Repository,
+    first_parent: bool,
+) -> Result<HashSet<Oid>, Box<dyn Error>> {
+    let head = repository.head()?.peel_to_commit()?.id();
+    get_reachable_commits_from(repository, head, first_parent)
+}
 
-    let tags: Vec<&str> = tags
+/// Collect every commit reachable from a commit
+/// # Arguments
+/// * `repository` - The repository to walk
+/// * `tip` - The commit to start from
+/// * `first_parent` - Only follow the first parent of merge commits
+/// # Returns
+/// The ids of the reachable commits
+/// # Errors
+/// Returns an error if the history cannot be walked
+fn get_reachable_commits_from(
+    repository: &Repository,
+    tip: Oid,
+    first_parent: bool,
+) -> Result<HashSet<Oid>, Box<dyn Error>> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.push(tip)?;
+    if first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+
+    let commits = revwalk.collect::<Result<HashSet<_>, _>>()?;
+    Ok(commits)
+}
+
+/// Find the latest tag on every branch matching a glob
+///
+/// Both local branches and the tracking branches of the remote are searched, so a CI checkout
+/// that only fetched the remote branches still finds them. A local branch takes precedence over
+/// the remote branch with the same name.
+/// # Arguments
+/// * `repository` - The repository containing the branches and tags
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - The tag names to search
+/// * `pattern` - The glob the branch names must match (e.g. `release/*`)
+/// * `remote` - The remote whose tracking branches are searched
+/// * `filter` - The filter to apply to the tags
+/// # Returns
+/// A map from each branch name to its latest tag, or null if it has none
+/// # Errors
+/// Returns an error if the branches cannot be listed or their history cannot be walked
+fn get_branch_latest_tags(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    pattern: &Pattern,
+    remote: &str,
+    filter: &TagFilter,
+) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let remote_prefix = format!("{remote}/");
+    let mut branches = BTreeMap::new();
+    for branch in repository.branches(None)? {
+        let (branch, branch_type) = branch?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        let name = match branch_type {
+            git2::BranchType::Local => name,
+            git2::BranchType::Remote => match name.strip_prefix(&remote_prefix) {
+                Some(name) if name != "HEAD" => name,
+                _ => continue,
+            },
+        };
+        if pattern.matches(name) {
+            let tip = branch.get().peel_to_commit()?.id();
+            if branch_type == git2::BranchType::Local || !branches.contains_key(name) {
+                branches.insert(name.to_string(), tip);
+            }
+        }
+    }
+
+    branches
         .into_iter()
-        .filter(|tag| tag_pattern.is_match(tag))
-        .collect();
+        .map(|(name, tip)| {
+            let latest_tag = find_latest_tag_from(repository, ref_namespace, tags, tip, filter)?;
+            Ok((name, latest_tag.into()))
+        })
+        .collect()
+}
 
-    let latest_tag = tags.iter().max_by(|a, b| {
-        let a_version =
-            semver::Version::parse(&a[tag_prefix.len()..]).unwrap_or(semver::Version::new(0, 0, 0));
-        let b_version =
-            semver::Version::parse(&b[tag_prefix.len()..]).unwrap_or(semver::Version::new(0, 0, 0));
-        a_version.cmp(&b_version)
-    });
+/// Find the latest tag whose commit is reachable from a branch
+/// # Arguments
+/// * `repository` - The repository containing the tags
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - The tag names to search
+/// * `branch` - The branch, looked up locally and then on the remote
+/// * `remote` - The remote whose tracking branch is used when there is no local branch
+/// * `filter` - The filter to apply to the tags
+/// # Returns
+/// The latest matching tag on the branch, or `None` if there is none
+/// # Errors
+/// Returns an error if the branch cannot be resolved or its history cannot be walked
+fn find_branch_latest_tag(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    branch: &str,
+    remote: &str,
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let tip = find_branch_tip(repository, branch, remote)?;
+    find_latest_tag_from(repository, ref_namespace, tags, tip, filter)
+}
 
-    let latest_tag = if let Some(tag) = latest_tag {
-        tag.to_string()
-    } else {
-        return Err(format!("No tags found matching pattern: {}", tag_pattern).into());
-    };
+/// Find the latest tag whose commit is reachable from a commit
+/// # Arguments
+/// * `repository` - The repository containing the tags
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - The tag names to search
+/// * `tip` - The commit whose history the tags must be in
+/// * `filter` - The filter to apply to the tags
+/// # Returns
+/// The latest matching tag in the history, or `None` if there is none
+/// # Errors
+/// Returns an error if the history cannot be walked
+fn find_latest_tag_from(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    tip: Oid,
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let reachable = get_reachable_commits_from(repository, tip, false)?;
+    let tags = tags
+        .iter()
+        .copied()
+        .filter(|tag| {
+            resolve_tag_commit(repository, ref_namespace, tag)
+                .is_some_and(|commit| reachable.contains(&commit))
+        })
+        .collect::<Vec<_>>();
+    find_latest_tag(&tags, filter)
+}
+
+/// Get the name of the checked-out branch
+/// # Arguments
+/// * `repository` - The repository whose HEAD is checked
+/// * `branch` - The name of the current branch, if given instead of detecting it from HEAD
+/// # Returns
+/// The branch name, or `HEAD` if HEAD is detached and no branch can be determined
+/// # Errors
+/// Returns an error if no branch is given and HEAD cannot be resolved
+fn get_current_branch(
+    repository: &Repository,
+    branch: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(branch) = branch {
+        return Ok(branch.to_string());
+    }
 
-    Ok(latest_tag.to_owned())
+    // A bare repository has no checkout, and its HEAD may name a branch that does not exist
+    let head = repository.head().map_err(|e| {
+        format!(
+            "Failed to resolve HEAD: {}. Use --branch to name the current branch.",
+            e.message()
+        )
+    })?;
+    match resolve_branch_name(head.shorthand(), |name| env::var(name).ok()) {
+        Some(branch_name) => Ok(branch_name),
+        None => {
+            let message = "HEAD is detached and no branch could be determined from GITHUB_HEAD_REF or GITHUB_REF. Treating it as a prerelease branch.";
+            println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+            warnings::record("detached_head", None, message);
+            Ok("HEAD".to_string())
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Decide whether the checked-out branch is a prerelease branch
+/// # Arguments
+/// * `branch_name` - The name of the current branch
+/// * `release_branch` - The name of the release branch
+/// * `rules` - Patterns that override the comparison with the release branch
+/// # Returns
+/// `true` if prerelease tags should be selected on the current branch
+fn is_prerelease_branch(branch_name: &str, release_branch: &str, rules: &BranchRules) -> bool {
+    let (mode, reason) = get_branch_mode(branch_name, release_branch, rules);
+    match mode {
+        BranchMode::Prerelease => {
+            println!("Current branch ({branch_name}) {reason}. Including only prerelease tags.")
+        }
+        BranchMode::Stable => {
+            println!("Current branch ({branch_name}) {reason}. Excluding prerelease tags.")
+        }
+    }
 
-    #[test]
-    fn test_get_latest_tag() {
-        // Check that the highest stable tag is returned even with an older prerelease of the same major.minor.patch version
-        let tags = vec!["v1.0.0", "v1.2.0", "v1.1.5", "v2.0.0-beta.0", "v2.0.0"];
-        let latest_tag = get_latest_tag(tags, "v", "beta", false).unwrap();
-        assert_eq!(latest_tag, "v2.0.0");
+    mode == BranchMode::Prerelease
+}
 
-        // Check that the highest stable tag is returned even with a newer prerelease of the same major.minor version
-        let tags = vec!["v1.0.0", "v1.2.0", "v1.1.5", "v2.0.0-beta.0"];
-        let latest_tag = get_latest_tag(tags, "v", "beta", false).unwrap();
-        assert_eq!(latest_tag, "v1.2.0");
+/// Decide whether a workflow triggered by pushing a tag selects prerelease tags
+/// # Arguments
+/// * `tag` - The name of the tag that triggered the workflow
+/// * `mode` - The mode for tag-triggered workflows
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// `true` if prerelease tags should be selected
+fn is_prerelease_trigger(tag: &str, mode: OnTagMode, filter: &TagFilter) -> bool {
+    let prerelease = match mode {
+        OnTagMode::Stable => false,
+        OnTagMode::Prerelease => true,
+        OnTagMode::Infer => match parse_tag_version(tag, filter) {
+            Some(version) => !version.pre.is_empty(),
+            None => {
+                let message = format!(
+                    "The triggering tag {tag} is not a version, so whether it is a prerelease cannot be inferred. Treating it as a prerelease."
+                );
+                println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+                warnings::record("triggering_tag", Some(tag), &message);
+                true
+            }
+        },
+    };
+    if prerelease {
+        println!("Triggered by the tag {tag}. Including only prerelease tags.");
+    } else {
+        println!("Triggered by the tag {tag}. Excluding prerelease tags.");
+    }
+
+    prerelease
+}
 
-        // Check that the highest tag (including prerelease) is returned
-        let tags = vec!["v1.0.0-beta.0", "v1.0.0", "v1.1.0-beta.0"];
-        let latest_tag = get_latest_tag(tags, "v", "beta", true).unwrap();
-        assert_eq!(latest_tag, "v1.1.0-beta.0");
+/// Derive a prerelease suffix from a branch name (e.g. `feature/login` becomes `feature-login`)
+///
+/// Prerelease identifiers may only contain ASCII letters, digits and hyphens, so the name is
+/// lowercased and every run of other characters becomes a single hyphen.
+/// # Arguments
+/// * `branch_name` - The name of the branch
+/// # Returns
+/// The suffix, or `None` if the name has no letters or digits
+fn get_branch_suffix(branch_name: &str) -> Option<String> {
+    let suffix = branch_name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    (!suffix.is_empty()).then_some(suffix)
+}
+
+/// Decide whether a branch selects stable or prerelease tags
+///
+/// Stable patterns are checked first, then prerelease patterns. Any other branch except the
+/// release branch uses the default mode, or selects prerelease tags without one.
+/// # Arguments
+/// * `branch` - The name of the branch
+/// * `release_branch` - The name of the release branch
+/// * `rules` - Patterns that override the comparison with the release branch
+/// # Returns
+/// The mode, and the reason it was chosen for logging (e.g. `is the release branch`)
+fn get_branch_mode(
+    branch: &str,
+    release_branch: &str,
+    rules: &BranchRules,
+) -> (BranchMode, String) {
+    if let Some(pattern) = rules.stable.iter().find(|pattern| pattern.matches(branch)) {
+        return (
+            BranchMode::Stable,
+            format!("matches the stable branch pattern ({pattern})"),
+        );
+    }
+    if let Some(pattern) = rules
+        .prerelease
+        .iter()
+        .find(|pattern| pattern.matches(branch))
+    {
+        return (
+            BranchMode::Prerelease,
+            format!("matches the prerelease branch pattern ({pattern})"),
+        );
+    }
+    if branch == release_branch {
+        return (BranchMode::Stable, "is the release branch".to_string());
+    }
+
+    match rules.default_mode {
+        Some(mode) => (mode, "matches no branch pattern".to_string()),
+        None => (
+            BranchMode::Prerelease,
+            format!("is not the release branch ({release_branch})"),
+        ),
+    }
+}
+
+/// Find the commit a branch points to
+/// # Arguments
+/// * `repository` - The repository containing the branch
+/// * `branch` - The name of the branch, looked up locally and then on the remote
+/// * `remote` - The remote whose tracking branch is used when there is no local branch
+/// # Returns
+/// The id of the commit at the tip of the branch
+/// # Errors
+/// Returns an error if the branch cannot be found
+fn find_branch_tip(
+    repository: &Repository,
+    branch: &str,
+    remote: &str,
+) -> Result<Oid, Box<dyn Error>> {
+    let reference = repository
+        .find_reference(&format!("refs/heads/{branch}"))
+        .or_else(|_| repository.find_reference(&format!("refs/remotes/{remote}/{branch}")))
+        .map_err(|_| format!("Branch {branch} not found locally or on {remote}"))?;
+    Ok(reference.peel_to_commit()?.id())
+}
+
+/// Decide whether a pull request selects prerelease tags from the branch it merges into
+///
+/// Only pull requests into the release branch select stable tags, and only if HEAD already
+/// contains the tip of the release branch (their merge base is that tip). A branch that is
+/// behind the release branch would release without its latest changes.
+/// # Arguments
+/// * `repository` - The repository whose HEAD is checked
+/// * `target_branch` - The branch the pull request merges into
+/// * `release_branch` - The name of the release branch
+/// * `remote` - The remote whose tracking branch is used when there is no local branch
+/// # Returns
+/// `true` if prerelease tags should be selected
+/// # Errors
+/// Returns an error if HEAD or the target branch cannot be resolved
+fn is_prerelease_target(
+    repository: &Repository,
+    target_branch: &str,
+    release_branch: &str,
+    remote: &str,
+) -> Result<bool, Box<dyn Error>> {
+    if target_branch != release_branch {
+        println!(
+            "Target branch ({target_branch}) is not the release branch ({release_branch}). Including only prerelease tags."
+        );
+        return Ok(true);
+    }
+
+    let tip = find_branch_tip(repository, target_branch, remote)?;
+    let head = repository.head()?.peel_to_commit()?.id();
+    match repository.merge_base(head, tip) {
+        Ok(merge_base) if merge_base == tip => {
+            println!(
+                "Target branch ({target_branch}) is the release branch and HEAD contains its tip. Excluding prerelease tags."
+            );
+            Ok(false)
+        }
+        Ok(_) => {
+            println!(
+                "HEAD does not contain the tip of the target branch ({target_branch}). Including only prerelease tags."
+            );
+            Ok(true)
+        }
+        Err(_) => {
+            println!(
+                "HEAD shares no history with the target branch ({target_branch}). Including only prerelease tags."
+            );
+            Ok(true)
+        }
+    }
+}
+
+/// Check that the commit of a tag is in the history of a branch
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `branch` - The branch the tag must be on, looked up locally and then on the remote
+/// * `remote` - The remote whose tracking branch is used when there is no local branch
+/// # Errors
+/// Returns an error if the tag or branch cannot be resolved, or the tag is not on the branch
+fn check_tag_on_branch(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    branch: &str,
+    remote: &str,
+) -> Result<(), Box<dyn Error>> {
+    let commit = resolve_tag_commit(repository, ref_namespace, tag)
+        .ok_or_else(|| format!("Tag {tag} does not point to a local commit"))?;
+    let tip = find_branch_tip(repository, branch, remote)?;
+
+    if tip != commit && !repository.graph_descendant_of(tip, commit)? {
+        return Err(format!("Tag {tag} is not on the release branch ({branch})").into());
+    }
+
+    Ok(())
+}
+
+/// Resolve the name of the checked-out branch
+///
+/// On a detached checkout (common in CI) git reports the shorthand `HEAD`, which would never
+/// equal the release branch. In that case the branch is taken from the GitHub actions
+/// environment instead: `GITHUB_HEAD_REF` for pull requests, then `GITHUB_REF` when it
+/// refers to a branch.
+/// # Arguments
+/// * `shorthand` - The shorthand name of HEAD as reported by git
+/// * `env_var` - Looks up an environment variable
+/// # Returns
+/// The branch name, or `None` if HEAD is detached and the environment does not name a branch
+fn resolve_branch_name(
+    shorthand: Option<&str>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    if let Some(shorthand) = shorthand
+        && shorthand != "HEAD"
+    {
+        return Some(shorthand.to_string());
+    }
+
+    if let Some(head_ref) = env_var("GITHUB_HEAD_REF").filter(|head_ref| !head_ref.is_empty()) {
+        return Some(head_ref);
+    }
+
+    env_var("GITHUB_REF")?
+        .strip_prefix("refs/heads/")
+        .map(str::to_string)
+}
+
+/// Get the `owner/name` slug of a repository from the URL of a remote
+/// # Arguments
+/// * `url` - The URL of the remote, in SSH (`git@github.com:owner/name.git`), `ssh://` or
+///   HTTPS (`https://github.com/owner/name`) form
+/// # Returns
+/// The slug, or `None` if the URL does not end with an owner and a name
+fn parse_repo_slug(url: &str) -> Option<String> {
+    let path = match url.split_once("://") {
+        // Drop the credentials and host (e.g. `user@github.com:22/`)
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, name) = path.rsplit_once('/')?;
+    let owner = owner.rsplit('/').next()?;
+    if owner.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some(format!("{owner}/{name}"))
+}
+
+/// Get the tag whose push triggered the workflow, from the `GITHUB_REF` of the environment
+/// # Arguments
+/// * `env_var` - Looks up an environment variable
+/// # Returns
+/// The tag name, or `None` if the workflow was not triggered by a tag
+fn get_triggering_tag(env_var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    env_var("GITHUB_REF")?
+        .strip_prefix("refs/tags/")
+        .map(str::to_string)
+}
+
+/// Check whether a prerelease can be promoted to a stable release from HEAD
+///
+/// The prerelease is promotable only if its commit is HEAD, so the stable release contains
+/// exactly what was tested as the prerelease.
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the prerelease tag
+/// # Returns
+/// `true` if the commit of the tag is HEAD
+fn is_promotable(repository: &Repository, ref_namespace: &str, tag: &str) -> bool {
+    match get_ahead_behind(repository, ref_namespace, tag) {
+        Some((0, 0)) => {
+            println!("HEAD has no changes since {tag}. It can be promoted.");
+            true
+        }
+        Some((ahead, 0)) => {
+            println!("HEAD is {ahead} commit(s) ahead of {tag}. It cannot be promoted.");
+            false
+        }
+        Some(_) => {
+            println!("{tag} is not in the history of HEAD. It cannot be promoted.");
+            false
+        }
+        None => {
+            println!("The commit of {tag} cannot be resolved. It cannot be promoted.");
+            false
+        }
+    }
+}
+
+/// Count the commits HEAD and a tag each have that the other does not
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// The number of commits HEAD is ahead of and behind the tagged commit, or `None` if HEAD or
+/// the tag cannot be resolved locally
+fn get_ahead_behind(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+) -> Option<(usize, usize)> {
+    let head = repository.head().ok()?.peel_to_commit().ok()?.id();
+    let commit = resolve_tag_commit(repository, ref_namespace, tag)?;
+    repository.graph_ahead_behind(head, commit).ok()
+}
+
+/// Get the messages of the commits on HEAD since a tag, stopping at the scan limit
+///
+/// One commit past the limit is walked to tell whether the scan was cut short, in which case a
+/// `scan_truncated` warning is raised.
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag; if it cannot be resolved locally the whole history is used
+/// * `max_scan_commits` - Scan at most this many commits, or `None` to scan them all
+/// * `ignore_merges` - Leave out the messages of merge commits
+/// * `scanned_for` - What the commits are scanned for and what a cut short scan may get wrong,
+///   for the warning (e.g. `breaking changes`)
+/// # Returns
+/// The commit messages, newest first, or `None` if the history cannot be walked
+fn scan_commit_messages_since(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    max_scan_commits: Option<usize>,
+    ignore_merges: bool,
+    scanned_for: &str,
+) -> Option<Vec<String>> {
+    let mut messages = get_commit_messages_since(
+        repository,
+        ref_namespace,
+        tag,
+        max_scan_commits.map(|max| max.saturating_add(1)),
+        ignore_merges,
+    )?;
+    if let Some(max) = max_scan_commits
+        && messages.len() > max
+    {
+        messages.truncate(max);
+        let message =
+            format!("Only the latest {max} commits since {tag} were scanned for {scanned_for}.");
+        println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {message}");
+        warnings::record("scan_truncated", Some(tag), &message);
+    }
+    Some(messages)
+}
+
+/// Check whether any commit on HEAD since a tag is a breaking change
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag, usually the latest stable tag
+/// * `max_scan_commits` - Scan at most this many commits, or `None` to scan them all
+/// * `ignore_merges` - Leave out merge commits
+/// # Returns
+/// Whether a conventional commit since the tag is marked as breaking, or `None` if there is no
+/// tag or the history cannot be walked
+fn has_breaking_change_since(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    max_scan_commits: Option<usize>,
+    ignore_merges: bool,
+) -> Option<bool> {
+    if tag.is_empty() {
+        return None;
+    }
+    let messages = scan_commit_messages_since(
+        repository,
+        ref_namespace,
+        tag,
+        max_scan_commits,
+        ignore_merges,
+        "breaking changes, so an older one may be missed",
+    )?;
+    Some(count_commits(messages.iter().map(String::as_str)).breaking > 0)
+}
+
+/// Get the messages of the commits in a range
+/// # Arguments
+/// * `repository` - The repository containing the commits
+/// * `range` - The range as `FROM..TO`, where either end may be a tag, branch or commit
+/// * `ignore_merges` - Leave out the messages of merge commits
+/// # Returns
+/// The messages of the commits reachable from `TO` but not from `FROM`, newest first
+/// # Errors
+/// Returns an error if the range cannot be resolved or the history cannot be walked
+fn get_range_commit_messages(
+    repository: &Repository,
+    range: &str,
+    ignore_merges: bool,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk
+        .push_range(range)
+        .map_err(|e| format!("Failed to resolve the range {range}: {}", e.message()))?;
+
+    let mut messages = Vec::new();
+    for id in revwalk {
+        let commit = repository.find_commit(id?)?;
+        if !ignore_merges || commit.parent_count() <= 1 {
+            messages.push(String::from_utf8_lossy(commit.message_bytes()).into_owned());
+        }
+    }
+    Ok(messages)
+}
+
+/// Check that a tag is an annotated tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Errors
+/// Returns an error naming the tag if it is missing or a lightweight tag
+fn check_annotated_tag(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+) -> Result<(), Box<dyn Error>> {
+    let reference = repository
+        .find_reference(&format!("{ref_namespace}/{tag}"))
+        .map_err(|_| format!("Tag {tag} not found"))?;
+    if reference.peel_to_tag().is_err() {
+        return Err(format!(
+            "Tag {tag} is a lightweight tag, but --require-annotated requires an annotated tag"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Check that the version named in the annotation message of a tag is the version of the tag
+///
+/// The first version in the message is used (e.g. `1.2.0` in `Release v1.2.0`). Build metadata
+/// is ignored as it does not change the version.
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Errors
+/// Returns an error if the tag has no message, its message names no version or a different
+/// version, or the tag itself is not a semantic version
+fn check_message_version(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    filter: &TagFilter,
+) -> Result<(), Box<dyn Error>> {
+    let version = parse_tag_version(tag, filter)
+        .ok_or_else(|| format!("Failed to parse {tag} as a semantic version"))?;
+    let message = get_tag_message(repository, ref_namespace, tag)
+        .ok_or_else(|| format!("Tag {tag} has no annotation message to take a version from"))?;
+    // The version may follow a prefix such as `v`, but not be the end of a longer number
+    let pattern = Regex::new(
+        r"(?:^|[^0-9.])(?<version>\d+\.\d+\.\d+(?:-[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?(?:\+[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?)",
+    )?;
+    let message_version = pattern
+        .captures_iter(&message)
+        .find_map(|captures| Version::parse(&captures["version"]).ok())
+        .ok_or_else(|| format!("The message of tag {tag} does not name a version"))?;
+
+    if message_version.cmp_precedence(&version).is_ne() {
+        return Err(format!(
+            "The message of tag {tag} names version {message_version}, but the tag is version {version}"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Get the annotation message of a tag
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// The message of an annotated tag, or `None` for a lightweight or missing tag
+fn get_tag_message(repository: &Repository, ref_namespace: &str, tag: &str) -> Option<String> {
+    let reference = repository
+        .find_reference(&format!("{ref_namespace}/{tag}"))
+        .ok()?;
+    let tag = reference.peel_to_tag().ok()?;
+    tag.message().map(|message| message.trim_end().to_string())
+}
+
+/// Get the names of all tags in the local repository
+/// # Arguments
+/// * `repository` - The repository to read tags from
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// # Returns
+/// The tag names
+/// # Errors
+/// Returns an error if the tags cannot be read
+fn get_local_tag_names(
+    repository: &Repository,
+    ref_namespace: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let prefix = format!("{ref_namespace}/");
+    let mut tags = Vec::new();
+    for reference in repository.references_glob(&format!("{prefix}*"))? {
+        if let Some(tag) = reference?
+            .name()
+            .and_then(|name| name.strip_prefix(&prefix))
+        {
+            tags.push(tag.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+/// Get the names of all tags advertised by a remote without fetching them
+/// # Arguments
+/// * `repository` - The repository the remote is configured in
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `remote_name` - The name of the remote (e.g. "origin")
+/// # Returns
+/// The tag names advertised by the remote
+/// # Errors
+/// Returns an error if the remote is missing or cannot be contacted
+fn get_remote_tag_names(
+    repository: &Repository,
+    ref_namespace: &str,
+    remote_name: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut remote = repository
+        .find_remote(remote_name)
+        .map_err(|_| format!("Remote '{remote_name}' not found"))?;
+
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(token_callbacks()), None)
+        .map_err(|e| format!("Failed to connect to remote '{remote_name}': {e}"))?;
+
+    // Annotated tags are advertised twice, once more with a peeled `^{}` suffix
+    let prefix = format!("{ref_namespace}/");
+    let tags = connection
+        .list()?
+        .iter()
+        .filter_map(|head| head.name().strip_prefix(&prefix))
+        .filter(|name| !name.ends_with("^{}"))
+        .map(str::to_string)
+        .collect();
+
+    Ok(tags)
+}
+
+/// Push a tag to a remote
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `remote_name` - The name of the remote (e.g. "origin")
+/// * `tag` - The name of the tag
+/// # Errors
+/// Returns an error if the remote is missing or rejects the tag
+fn push_tag(
+    repository: &Repository,
+    ref_namespace: &str,
+    remote_name: &str,
+    tag: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut remote = repository
+        .find_remote(remote_name)
+        .map_err(|_| format!("Remote '{remote_name}' not found"))?;
+
+    let mut rejection = None;
+    let mut callbacks = token_callbacks();
+    callbacks.push_update_reference(|_reference, status| {
+        rejection = status.map(str::to_string);
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let refspec = format!("{ref_namespace}/{tag}:{ref_namespace}/{tag}");
+    remote
+        .push(&[refspec.as_str()], Some(&mut options))
+        .map_err(|e| format!("Failed to push tag {tag} to '{remote_name}': {e}"))?;
+    drop(options);
+
+    match rejection {
+        Some(status) => Err(format!("Remote '{remote_name}' rejected tag {tag}: {status}").into()),
+        None => Ok(()),
+    }
+}
+
+/// Build remote callbacks that authenticate with the workflow token when the remote requires it
+fn token_callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|_url, _username, allowed| match env::var("GITHUB_TOKEN") {
+        Ok(token) if allowed.is_user_pass_plaintext() => {
+            Cred::userpass_plaintext("x-access-token", &token)
+        }
+        _ => Cred::default(),
+    });
+    callbacks
+}
+
+/// Get the version strings stored in git notes
+/// # Arguments
+/// * `repository` - The repository containing the notes
+/// * `notes_ref` - The notes reference to read (e.g. "refs/notes/commits")
+/// # Returns
+/// The first line of every note, which is treated like a tag name
+/// # Errors
+/// Returns an error if the notes cannot be read
+fn get_note_versions(
+    repository: &Repository,
+    notes_ref: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let notes = repository
+        .notes(Some(notes_ref))
+        .map_err(|e| format!("Failed to read notes from {notes_ref}: {e}"))?;
+
+    let mut versions = Vec::new();
+    for note in notes {
+        let (_, annotated_id) = note?;
+        let note = repository.find_note(Some(notes_ref), annotated_id)?;
+        if let Some(version) = note.message().and_then(|message| message.lines().next()) {
+            versions.push(version.trim().to_string());
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Find tags whose version is lower than a tag on an older commit
+/// # Arguments
+/// * `tags` - The tags to check
+/// * `filter` - The criteria a tag must meet to be checked
+/// * `commit_times` - The commit time of each tag; tags without one are skipped
+/// # Returns
+/// Each tag whose version went backwards, paired with the highest tag on an older commit, in
+/// commit order
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn find_version_regressions(
+    tags: &[&str],
+    filter: &TagFilter,
+    commit_times: &HashMap<String, i64>,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut timed = get_matching_tags(tags, filter)?
+        .into_iter()
+        .filter_map(|tag| Some((*commit_times.get(tag)?, tag)))
+        .collect::<Vec<_>>();
+    timed.sort_by_key(|(time, _)| *time);
+
+    let mut regressions = Vec::new();
+    let mut highest: Option<(i64, &str)> = None;
+    for (time, tag) in timed {
+        match highest {
+            Some((highest_time, highest_tag))
+                if compare_tags(tag, highest_tag, filter) == Ordering::Less =>
+            {
+                // Tags on the same commit have no order in time
+                if time > highest_time {
+                    regressions.push((tag.to_string(), highest_tag.to_string()));
+                }
+            }
+            _ => highest = Some((time, tag)),
+        }
+    }
+
+    Ok(regressions)
+}
+
+/// Find the prereleases missing from each prerelease series
+///
+/// The prereleases of a version in a channel (e.g. `v1.1.0-rc.1` and `v1.1.0-rc.3`) should be
+/// counted up from 1 without gaps. Every tag with the prefix is checked, whatever the channel.
+/// # Arguments
+/// * `tags` - The tags to check
+/// * `filter` - The prefix and component separator the tags use
+/// # Returns
+/// The tags missing below the highest counter of each series (e.g. `v1.1.0-rc.2`), in order
+fn find_missing_prereleases(tags: &[&str], filter: &TagFilter) -> Vec<String> {
+    let mut series: BTreeMap<(u64, u64, u64, String), HashSet<u64>> = BTreeMap::new();
+    for version in tags.iter().filter_map(|tag| parse_tag_version(tag, filter)) {
+        if let Some((channel, counter)) = version.pre.as_str().rsplit_once('.')
+            && let Ok(counter) = counter.parse::<u64>()
+        {
+            series
+                .entry((
+                    version.major,
+                    version.minor,
+                    version.patch,
+                    channel.to_string(),
+                ))
+                .or_default()
+                .insert(counter);
+        }
+    }
+
+    let mut missing = Vec::new();
+    for ((major, minor, patch, channel), counters) in series {
+        let highest = counters.iter().max().copied().unwrap_or_default();
+        for counter in (1..highest).filter(|counter| !counters.contains(counter)) {
+            let Ok(pre) = Prerelease::new(&format!("{channel}.{counter}")) else {
+                continue;
+            };
+            let version = Version {
+                pre,
+                ..Version::new(major, minor, patch)
+            };
+            missing.push(format_tag(&version, filter));
+        }
+    }
+    missing
+}
+
+/// Check that the tie-break can decide between the tags with the latest version
+/// # Arguments
+/// * `tags` - The tags to check
+/// * `filter` - The criteria tags must meet, including the tie-break
+/// # Errors
+/// Returns an error listing the tags if several have exactly the latest version and the
+/// tie-break orders them equally (e.g. `v1.2.3` and `v01.2.3` on the same commit)
+fn check_unresolved_tie(tags: &[&str], filter: &TagFilter) -> Result<(), Box<dyn Error>> {
+    let matching = get_matching_tags(tags, filter)?;
+    let Some(latest) = matching.first() else {
+        return Ok(());
+    };
+
+    let tied = matching
+        .iter()
+        .take_while(|tag| {
+            compare_tags(latest, tag, filter) == Ordering::Equal
+                && break_tie(latest, tag, filter) == Ordering::Equal
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    if tied.len() > 1 {
+        let tie_break = filter
+            .tie_break
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        return Err(format!(
+            "Tags {} have the same version and cannot be ordered by the {tie_break} tie-break",
+            tied.join(", ")
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Detect the prefix shared by the tags that look like versions (e.g. `v` for `v1.2.0`)
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `component_separator` - The separator between the major, minor and patch components
+/// # Returns
+/// The text before the version of every such tag, or `None` if there are no such tags or
+/// their prefixes differ
+fn detect_tag_prefix(tags: &[String], component_separator: &str) -> Option<String> {
+    let separator = regex::escape(component_separator);
+    let version_pattern =
+        Regex::new(&format!(r"^(.*?)\d+{separator}\d+{separator}\d+(?:[-+]|$)")).ok()?;
+
+    let mut prefixes = tags
+        .iter()
+        .filter_map(|tag| Some(version_pattern.captures(tag)?.get(1)?.as_str()));
+    let prefix = prefixes.next()?;
+    prefixes
+        .all(|other| other == prefix)
+        .then(|| prefix.to_string())
+}
+
+/// Check whether the selected tag is the very first release
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// * `latest_tag` - The selected tag
+/// * `default_tag` - The tag used when no tags match, if any
+/// # Returns
+/// `true` if exactly one stable tag matches, or the selection fell back to the default tag
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn is_first_release(
+    tags: &[&str],
+    filter: &TagFilter,
+    latest_tag: &str,
+    default_tag: Option<&str>,
+) -> Result<bool, Box<dyn Error>> {
+    if default_tag == Some(latest_tag) {
+        return Ok(true);
+    }
+
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    Ok(get_matching_tags(tags, &stable_filter)?.len() == 1)
+}
+
+/// Find the prerelease tags that a comparator ranks above the stable tag of the same version
+///
+/// `v1.0.0-rc.1` must always rank below `v1.0.0`; this guards against a comparator that breaks
+/// that rule.
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be checked, whose prerelease setting is ignored
+/// * `compare` - The comparator to check (e.g. `compare_tags`)
+/// # Returns
+/// Each violation as `<prerelease> > <stable>`, or nothing if the ordering is sound
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn find_ordering_violations(
+    tags: &[&str],
+    filter: &TagFilter,
+    compare: impl Fn(&str, &str, &TagFilter) -> Ordering,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let versions_matching = |prerelease| -> Result<Vec<(&str, Version)>, Box<dyn Error>> {
+        let candidate_filter = TagFilter {
+            prerelease,
+            ..filter.clone()
+        };
+        Ok(get_matching_tags(tags, &candidate_filter)?
+            .into_iter()
+            .filter_map(|tag| Some((tag, parse_tag_version(tag, filter)?)))
+            .collect())
+    };
+    let stable = versions_matching(false)?;
+    let prereleases = versions_matching(true)?;
+
+    let mut violations = Vec::new();
+    for (prerelease_tag, prerelease_version) in prereleases
+        .iter()
+        .filter(|(_, version)| !version.pre.is_empty())
+    {
+        let core = Version::new(
+            prerelease_version.major,
+            prerelease_version.minor,
+            prerelease_version.patch,
+        );
+        for (stable_tag, _) in stable
+            .iter()
+            .filter(|(_, version)| version.cmp_precedence(&core).is_eq())
+        {
+            if compare(prerelease_tag, stable_tag, filter).is_gt() {
+                violations.push(format!("{prerelease_tag} > {stable_tag}"));
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// Find the stable tags with no prerelease tag of the same version
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a stable tag must meet to be checked, whose prerelease settings
+///   are ignored
+/// # Returns
+/// The stable tags with no prerelease of any channel before them (e.g. `v1.0.0` without
+/// `v1.0.0-rc.1`), newest first
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn find_stable_tags_without_prerelease<'a>(
+    tags: &[&'a str],
+    filter: &TagFilter,
+) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    let prerelease_filter = TagFilter {
+        prerelease: true,
+        any_prerelease: true,
+        ..filter.clone()
+    };
+    let prerelease_cores = get_matching_tags(tags, &prerelease_filter)?
+        .into_iter()
+        .filter_map(|tag| parse_tag_version(tag, filter))
+        .map(|version| Version::new(version.major, version.minor, version.patch))
+        .collect::<HashSet<_>>();
+
+    Ok(get_matching_tags(tags, &stable_filter)?
+        .into_iter()
+        .filter(|tag| {
+            parse_tag_version(tag, filter).is_some_and(|version| {
+                !prerelease_cores.contains(&Version::new(
+                    version.major,
+                    version.minor,
+                    version.patch,
+                ))
+            })
+        })
+        .collect())
+}
+
+/// Choose the prerelease channels reported by `--all-channels`
+/// # Arguments
+/// * `channels` - The channels given with `--channels`
+/// * `topic_channels` - The channels read from the repository topics
+/// * `prerelease_suffix` - The prerelease suffix, used when no channels are given
+/// # Returns
+/// The given channels, otherwise the topic channels, otherwise the prerelease suffix
+fn get_channels(
+    channels: Vec<String>,
+    topic_channels: Vec<String>,
+    prerelease_suffix: &str,
+) -> Vec<String> {
+    if !channels.is_empty() {
+        channels
+    } else if !topic_channels.is_empty() {
+        topic_channels
+    } else if !prerelease_suffix.is_empty() {
+        vec![prerelease_suffix.to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Find the latest stable tag and the latest prerelease tag of each channel
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The base criteria, whose prerelease settings are overridden per channel
+/// * `channels` - The prerelease suffixes to report (e.g. alpha, beta, rc)
+/// # Returns
+/// A JSON object mapping `stable` and each channel to its latest tag, or null if it has none
+/// # Errors
+/// Returns an error if a tag pattern is invalid
+fn get_all_channel_tags(
+    tags: &[&str],
+    filter: &TagFilter,
+    channels: &[String],
+) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let mut latest_tags = Map::new();
+
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    latest_tags.insert(
+        "stable".to_string(),
+        find_latest_tag(tags, &stable_filter)?.into(),
+    );
+
+    for channel in channels {
+        let channel_filter = TagFilter {
+            prerelease: true,
+            prerelease_suffix: channel.clone(),
+            ..filter.clone()
+        };
+        latest_tags.insert(
+            channel.clone(),
+            find_latest_tag(tags, &channel_filter)?.into(),
+        );
+    }
+
+    Ok(latest_tags)
+}
+
+/// Group the matching tags by their release channel
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The base criteria, whose prerelease settings are overridden to include every
+///   channel
+/// # Returns
+/// A JSON object mapping `stable` and each prerelease channel found (e.g. `rc`) to its tags,
+/// newest first
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn group_tags_by_channel(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    let prerelease_filter = TagFilter {
+        prerelease: true,
+        any_prerelease: true,
+        ..filter.clone()
+    };
+
+    let mut groups = BTreeMap::<String, Vec<&str>>::new();
+    groups.insert(
+        "stable".to_string(),
+        get_matching_tags(tags, &stable_filter)?,
+    );
+    for tag in get_matching_tags(tags, &prerelease_filter)? {
+        if let Some(version) = parse_tag_version(tag, filter) {
+            groups.entry(get_channel(&version)).or_default().push(tag);
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(channel, tags)| (channel, Value::from(tags)))
+        .collect())
+}
+
+/// Count the prerelease tags of a core version
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The criteria prerelease tags must meet, including the prerelease suffix
+/// * `version` - A version with the core version to count, whatever its prerelease
+/// # Returns
+/// The number of matching prerelease tags with the same major, minor and patch version (e.g. 3
+/// for `v1.3.0-rc.1`, `v1.3.0-rc.2` and `v1.3.0-rc.3`)
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn count_prerelease_iterations(
+    tags: &[&str],
+    filter: &TagFilter,
+    version: &Version,
+) -> Result<usize, Box<dyn Error>> {
+    let prerelease_filter = TagFilter {
+        prerelease: true,
+        ..filter.clone()
+    };
+    Ok(get_matching_tags(tags, &prerelease_filter)?
+        .into_iter()
+        .filter_map(|tag| parse_tag_version(tag, filter))
+        .filter(|prerelease| {
+            (prerelease.major, prerelease.minor, prerelease.patch)
+                == (version.major, version.minor, version.patch)
+        })
+        .count())
+}
+
+/// Work out the tag for the next prerelease of a core version
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The criteria prerelease tags must meet, including the prerelease suffix
+/// * `target_version` - The core version to create a prerelease of, or `None` for the
+///   version of the latest prerelease
+/// # Returns
+/// The tag with the counter after the highest existing prerelease of the core version (e.g.
+/// `v1.2.0-rc.4` after `v1.2.0-rc.3`), or counter 1 if the core version has no prereleases
+/// # Errors
+/// Returns an error if the prerelease suffix is empty, or no target version is given and there
+/// are no prerelease tags to take it from
+fn get_next_prerelease_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+    target_version: Option<&Version>,
+) -> Result<String, Box<dyn Error>> {
+    let prerelease_filter = TagFilter {
+        prerelease: true,
+        ..filter.clone()
+    };
+    let versions = get_matching_tags(tags, &prerelease_filter)?
+        .into_iter()
+        .filter_map(|tag| parse_tag_version(tag, filter))
+        .collect::<Vec<_>>();
+
+    let core = |version: &Version| Version::new(version.major, version.minor, version.patch);
+    let target = match target_version {
+        Some(target_version) => core(target_version),
+        None => versions.first().map(core).ok_or(
+            "No prerelease tags found. Use --target-version to start a new prerelease series",
+        )?,
+    };
+
+    let counter = versions
+        .iter()
+        .filter(|version| core(version) == target)
+        .filter_map(|version| version.pre.rsplit('.').next()?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let next = Version {
+        pre: Prerelease::new(&format!("{}.{counter}", filter.prerelease_suffix))?,
+        ..target
+    };
+    Ok(format_tag(&next, filter))
+}
+
+/// Describe the change from one version to another
+/// # Arguments
+/// * `previous` - The earlier version
+/// * `current` - The later version
+/// # Returns
+/// The most significant component that differs: `major`, `minor`, `patch` or `prerelease`, or
+/// `none` if the versions only differ by build metadata
+fn get_version_delta(previous: &Version, current: &Version) -> &'static str {
+    if previous.major != current.major {
+        "major"
+    } else if previous.minor != current.minor {
+        "minor"
+    } else if previous.patch != current.patch {
+        "patch"
+    } else if previous.pre != current.pre {
+        "prerelease"
+    } else {
+        "none"
+    }
+}
+
+/// Work out the tag of a nightly build from the latest tag and the date
+///
+/// The prerelease only contains the date, so every run on the same UTC day gives the same tag.
+/// # Arguments
+/// * `latest_tag` - The latest tag
+/// * `filter` - The filter describing how versions are written in the tags
+/// * `bump` - The component of the core version to increment, resolved from `Bump::Auto` already
+/// * `now` - The current time in seconds since the epoch
+/// # Returns
+/// The nightly tag (e.g. `v1.2.4-nightly.20240312` for `v1.2.3` on 12 March 2024), or `None` if
+/// the latest tag is not a valid semantic version
+fn get_nightly_tag(latest_tag: &str, filter: &TagFilter, bump: Bump, now: u64) -> Option<String> {
+    let version = parse_tag_version(latest_tag, filter)?;
+    let (year, month, day) = get_utc_date(now);
+    let nightly = Version {
+        pre: Prerelease::new(&format!("nightly.{year:04}{month:02}{day:02}")).ok()?,
+        ..bump_version(&version, bump)
+    };
+    Some(format_tag(&nightly, filter))
+}
+
+/// Convert a time into its date in UTC
+/// # Arguments
+/// * `seconds` - The time in seconds since the epoch
+/// # Returns
+/// The year, month and day of the month
+fn get_utc_date(seconds: u64) -> (u64, u64, u64) {
+    // Count from 1 March 0000, so the leap day ends each 400 year era, 4 year cycle and year
+    let days = seconds / (24 * 60 * 60) + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Work out the tag a release should create next for the current branch
+///
+/// Stable mode bumps the latest tag. Prerelease mode increments the counter of the latest
+/// prerelease, unless its version has already been released as stable (or there is none), in
+/// which case a new series starts at the bumped latest stable version.
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The criteria tags must meet, selecting stable or prerelease mode
+/// * `latest_tag` - The selected latest tag, bumped when there is no stable tag
+/// * `bump` - The component to increment, resolved from `Bump::Auto` already
+/// # Returns
+/// The recommended tag (e.g. `v1.3.0`, or `v1.3.0-rc.5` after `v1.3.0-rc.4`)
+/// # Errors
+/// Returns an error if the tag pattern is invalid or the tag to bump is not a semantic version
+fn get_recommended_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+    latest_tag: &str,
+    bump: Bump,
+) -> Result<String, Box<dyn Error>> {
+    let parse = |tag: &str| {
+        parse_tag_version(tag, filter)
+            .ok_or_else(|| format!("Failed to parse {tag} as a semantic version"))
+    };
+    if !filter.prerelease {
+        return Ok(format_tag(&bump_version(&parse(latest_tag)?, bump), filter));
+    }
+
+    let stable_filter = TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    };
+    let latest_stable = find_latest_tag(tags, &stable_filter)?
+        .map(|tag| parse(&tag))
+        .transpose()?;
+    let latest_prerelease = find_latest_tag(tags, filter)?
+        .map(|tag| parse(&tag))
+        .transpose()?;
+
+    let target = match latest_prerelease {
+        Some(prerelease)
+            if latest_stable.as_ref().is_none_or(|stable| {
+                Version::new(prerelease.major, prerelease.minor, prerelease.patch)
+                    .cmp_precedence(stable)
+                    .is_gt()
+            }) =>
+        {
+            prerelease
+        }
+        _ => match latest_stable {
+            Some(stable) => bump_version(&stable, bump),
+            None => bump_version(&parse(latest_tag)?, bump),
+        },
+    };
+    get_next_prerelease_tag(tags, filter, Some(&target))
+}
+
+/// The placeholders a template can use
+const TEMPLATE_PLACEHOLDERS: [&str; 7] = [
+    "prefix",
+    "major",
+    "minor",
+    "patch",
+    "prerelease",
+    "build",
+    "version",
+];
+
+/// Format a tag with a template
+///
+/// `{prefix}`, `{major}`, `{minor}`, `{patch}`, `{prerelease}` and `{build}` are replaced by
+/// the parts of the tag, and `{version}` by the canonical version.
+/// # Arguments
+/// * `template` - The template (e.g. `{major}.{minor}`)
+/// * `tag` - The tag to format
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The template with its placeholders replaced
+/// # Errors
+/// Returns an error if the tag is not a valid semantic version or the template uses an unknown
+/// placeholder
+fn render_template(
+    template: &str,
+    tag: &str,
+    filter: &TagFilter,
+) -> Result<String, Box<dyn Error>> {
+    let version = parse_tag_version(tag, filter)
+        .ok_or_else(|| format!("Failed to parse {tag} as a semantic version"))?;
+    let (prefix, _) = split_tag_prefix(tag, filter).unwrap_or_default();
+    let placeholder = Regex::new(r"\{(\w+)\}")?;
+
+    if let Some(unknown) = placeholder
+        .captures_iter(template)
+        .map(|captures| captures[1].to_string())
+        .find(|name| !TEMPLATE_PLACEHOLDERS.contains(&name.as_str()))
+    {
+        return Err(format!(
+            "Unknown placeholder {{{unknown}}} in the template, expected one of: {}",
+            TEMPLATE_PLACEHOLDERS.join(", ")
+        )
+        .into());
+    }
+
+    let formatted =
+        placeholder.replace_all(template, |captures: &regex::Captures| match &captures[1] {
+            "prefix" => prefix.to_string(),
+            "major" => version.major.to_string(),
+            "minor" => version.minor.to_string(),
+            "patch" => version.patch.to_string(),
+            "prerelease" => version.pre.to_string(),
+            "build" => version.build.to_string(),
+            _ => version.to_string(),
+        });
+    Ok(formatted.into_owned())
+}
+
+/// Count the tags in each prerelease channel
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The prefix and component separator the tags use
+/// # Returns
+/// The number of prerelease tags in each channel (e.g. `rc` for `v1.0.0-rc.1`), ordered by
+/// channel name
+fn count_prerelease_channels(tags: &[&str], filter: &TagFilter) -> BTreeMap<String, usize> {
+    let mut channels = BTreeMap::new();
+    for version in tags.iter().filter_map(|tag| parse_tag_version(tag, filter)) {
+        if !version.pre.is_empty() {
+            *channels.entry(get_channel(&version)).or_default() += 1;
+        }
+    }
+    channels
+}
+
+/// Count the distinct release lines among the matching tags
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The criteria a tag must meet to be counted
+/// # Returns
+/// The number of distinct major versions (e.g. `1.x`) and of distinct major and minor
+/// versions (e.g. `1.4.x`)
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn count_release_lines(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<(usize, usize), Box<dyn Error>> {
+    let versions = get_matching_tags(tags, filter)?
+        .into_iter()
+        .filter_map(|tag| parse_tag_version(tag, filter))
+        .collect::<Vec<_>>();
+    let major_lines = versions
+        .iter()
+        .map(|version| version.major)
+        .collect::<HashSet<_>>();
+    let minor_lines = versions
+        .iter()
+        .map(|version| (version.major, version.minor))
+        .collect::<HashSet<_>>();
+    Ok((major_lines.len(), minor_lines.len()))
+}
+
+/// Find the latest stable tag in each repository listed in a file
+///
+/// A repository that cannot be opened or has no matching tags is reported in its result
+/// rather than stopping the batch.
+/// # Arguments
+/// * `repos_file` - The file listing the repository paths, one per line; blank lines and lines
+///   starting with `#` are ignored, and relative paths are relative to the file
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `filter` - The criteria a tag must meet to be selected
+/// # Returns
+/// An object for each repository, in order, with its `repo` path, `latest_tag` and `error`,
+/// one of which is null
+/// # Errors
+/// Returns an error if the file cannot be read
+fn run_batch(
+    repos_file: &Path,
+    ref_namespace: &str,
+    filter: &TagFilter,
+) -> Result<Vec<Value>, Box<dyn Error>> {
+    let contents = fs::read_to_string(repos_file).map_err(|e| {
+        format!(
+            "Failed to read the repositories in {}: {e}",
+            repos_file.display()
+        )
+    })?;
+    let base = repos_file.parent().unwrap_or(Path::new(""));
+
+    let results = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let latest_tag = Repository::open(base.join(line))
+                .map_err(|e| format!("Failed to open the repository: {}", e.message()).into())
+                .and_then(|repository| {
+                    let tags = get_local_tag_names(&repository, ref_namespace)?;
+                    let tags = tags.iter().map(String::as_str).collect::<Vec<_>>();
+                    get_latest_tag(&tags, filter)
+                });
+            match latest_tag {
+                Ok(latest_tag) => json!({"repo": line, "latest_tag": latest_tag, "error": null}),
+                Err(e) => {
+                    println!("{WARNING_STYLE}Warning:{WARNING_STYLE:#} {line}: {e}");
+                    json!({"repo": line, "latest_tag": null, "error": e.to_string()})
+                }
+            }
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Find the tags that look like versions but do not follow the tag pattern
+///
+/// A tag looks like a version if it starts with one of the prefixes, ignoring case, followed
+/// by an optional `v` and a digit. Tags such as `latest` or those of other components are not
+/// reported, while `v1.2`, `V1.0.0` or `v1.0.0-rc1` are.
+/// # Arguments
+/// * `tags` - The tags to scan
+/// * `filter` - The prefixes and component separator the tags use
+/// # Returns
+/// The tags that look like versions but match neither the stable nor the prerelease pattern
+/// # Errors
+/// Returns an error if the patterns cannot be built
+fn find_nonconforming_tags<'a>(
+    tags: &[&'a str],
+    filter: &TagFilter,
+) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    let stable_pattern = get_tag_pattern(&TagFilter {
+        prerelease: false,
+        ..filter.clone()
+    })?;
+    let prerelease_pattern = get_tag_pattern(&TagFilter {
+        prerelease: true,
+        any_prerelease: true,
+        ..filter.clone()
+    })?;
+    let prefixes = filter
+        .tag_prefixes
+        .iter()
+        .map(|prefix| regex::escape(prefix))
+        .collect::<Vec<_>>();
+    let version_like = Regex::new(&format!(r"(?i)^(?:{})v?\d", prefixes.join("|")))?;
+
+    Ok(tags
+        .iter()
+        .filter(|tag| version_like.is_match(tag))
+        .filter(|tag| !stable_pattern.is_match(tag) && !prerelease_pattern.is_match(tag))
+        .copied()
+        .collect())
+}
+
+/// Create the error for a tag policy violation, announcing it to GitHub actions if requested
+/// # Arguments
+/// * `message` - The description of the violation
+/// * `annotations` - Print the violation as an `::error::` workflow annotation
+/// # Returns
+/// The error to fail the run with
+fn policy_error(message: String, annotations: bool) -> Box<dyn Error> {
+    if annotations {
+        println!("{}", format_annotation("error", &message));
+    }
+    message.into()
+}
+
+/// Check that every prerelease tag uses one of the allowed prerelease identifiers
+///
+/// Unlike selection, this looks at every tag with the prefix rather than only those matching
+/// the configured suffix, so typos such as `rcc` or `bета` are caught.
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The filter describing how versions are written in the tags
+/// * `allowed` - The allowed prerelease identifiers (e.g. alpha, beta, rc)
+/// # Errors
+/// Returns an error listing the tags that use an identifier outside the list
+fn check_allowed_prereleases(
+    tags: &[&str],
+    filter: &TagFilter,
+    allowed: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let separator = regex::escape(&filter.component_separator);
+    let prerelease_pattern = Regex::new(&format!(r"^\d+{separator}\d+{separator}\d+-([^+]+)"))?;
+
+    let disallowed = tags
+        .iter()
+        .filter(|tag| {
+            let Some((_, version)) = split_tag_prefix(tag, filter) else {
+                return false;
+            };
+            let Some(captures) = prerelease_pattern.captures(version) else {
+                return false;
+            };
+            let channel = get_prerelease_channel(&captures[1]);
+            !allowed.contains(&channel)
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    if !disallowed.is_empty() {
+        return Err(format!(
+            "Tags use prerelease identifiers that are not allowed ({}): {}",
+            allowed.join(", "),
+            disallowed.join(", ")
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Read the versions listed in a yanked versions file
+/// # Arguments
+/// * `path` - The file, with one version per line; blank lines and lines starting with `#` are
+///   ignored
+/// # Returns
+/// The yanked versions
+/// # Errors
+/// Returns an error if the file cannot be read or a line is not a valid semantic version
+fn read_yanked_versions(path: &Path) -> Result<Vec<Version>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Failed to read the yanked versions in {}: {e}",
+            path.display()
+        )
+    })?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            Version::parse(line).map_err(|e| {
+                format!(
+                    "Invalid version on line {number} of {}: {e}",
+                    path.display()
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Read the version requirements in a constraints file
+///
+/// Each line holds requirements in the Cargo syntax (e.g. `>=1.2, <2`), where `!=1.5.0` also
+/// excludes a single version.
+/// # Arguments
+/// * `path` - The file, with requirements on each line; blank lines and lines starting with `#`
+///   are ignored
+/// # Returns
+/// The constraints, each of which must be met
+/// # Errors
+/// Returns an error if the file cannot be read or a line holds an invalid requirement
+fn read_constraints(path: &Path) -> Result<Vec<Constraint>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read the constraints in {}: {e}", path.display()))?;
+    let mut constraints = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        constraints.extend(parse_constraints(line).map_err(|e| {
+            format!(
+                "Invalid constraint on line {} of {}: {e}",
+                number + 1,
+                path.display()
+            )
+        })?);
+    }
+    Ok(constraints)
+}
+
+/// Parse comma separated requirements in the Cargo syntax, where `!=1.5.0` excludes a version
+/// # Arguments
+/// * `requirements` - The requirements (e.g. `>=1.2, <2, !=1.5.0`)
+/// # Returns
+/// The constraints, each of which must be met
+/// # Errors
+/// Returns an error if a requirement is invalid
+fn parse_constraints(requirements: &str) -> Result<Vec<Constraint>, semver::Error> {
+    requirements
+        .split(',')
+        .map(str::trim)
+        .map(|part| match part.strip_prefix("!=") {
+            Some(excluded) => Version::parse(excluded.trim()).map(Constraint::Exclude),
+            None => VersionReq::parse(part).map(Constraint::Require),
+        })
+        .collect()
+}
+
+/// Parse a version range into constraints
+/// # Arguments
+/// * `range` - The range (e.g. `^1.2.0`, `~1.2.0 || ^2` or `>=1.2,<2`)
+/// * `syntax` - The syntax the range is written in
+/// # Returns
+/// The constraints, each of which must be met
+/// # Errors
+/// Returns an error if the range is invalid or has no Cargo equivalent
+fn parse_range(range: &str, syntax: RangeSyntax) -> Result<Vec<Constraint>, Box<dyn Error>> {
+    let invalid = |e: &dyn fmt::Display| format!("Invalid range {range}: {e}");
+    let mut alternatives = translate_range(range, syntax)
+        .map_err(|e| invalid(&e))?
+        .iter()
+        .map(|requirements| parse_constraints(requirements).map_err(|e| invalid(&e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if alternatives.len() == 1 {
+        return Ok(alternatives.remove(0));
+    }
+    Ok(vec![Constraint::AnyOf(alternatives)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use selection::{find_lowest_tag, find_previous_tag};
+
+    fn filter(tag_prefix: &str, prerelease_suffix: &str, prerelease: bool) -> TagFilter {
+        TagFilter {
+            tag_prefixes: vec![tag_prefix.to_string()],
+            prerelease_suffix: prerelease_suffix.to_string(),
+            prerelease,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_all_channel_tags() {
+        let tags = vec![
+            "v1.0.0",
+            "v1.1.0",
+            "v1.2.0-alpha.1",
+            "v1.2.0-alpha.2",
+            "v1.1.0-beta.3",
+            "v1.2.0-beta.1",
+            "v1.1.0-rc.1",
+        ];
+        let channels = ["alpha", "beta", "rc", "dev"].map(String::from);
+        let all_channels =
+            get_all_channel_tags(&tags, &filter("v", "beta", false), &channels).unwrap();
+
+        assert_eq!(all_channels["stable"], "v1.1.0");
+        assert_eq!(all_channels["alpha"], "v1.2.0-alpha.2");
+        assert_eq!(all_channels["beta"], "v1.2.0-beta.1");
+        assert_eq!(all_channels["rc"], "v1.1.0-rc.1");
+        assert_eq!(all_channels["dev"], Value::Null);
+        assert_eq!(all_channels.len(), 5);
+    }
+
+    #[cfg(feature = "github-api")]
+    #[test]
+    fn test_get_all_channel_tags_from_topics() {
+        let tags = vec!["v1.0.0", "v1.1.0-beta.1", "v1.1.0-rc.1"];
+        let mut api = github::MockApi::default();
+        api.responses.insert(
+            "/repos/owner/tool/topics".to_string(),
+            r#"{"names": ["rust", "channel-rc"]}"#.to_string(),
+        );
+
+        // Check that a channel only configured as a topic replaces the prerelease suffix
+        let topic_channels = github::get_topic_channels(&api, "owner/tool").unwrap();
+        let channels = get_channels(Vec::new(), topic_channels, "beta");
+        assert_eq!(channels, ["rc"]);
+        let all_channels =
+            get_all_channel_tags(&tags, &filter("v", "beta", false), &channels).unwrap();
+        assert_eq!(all_channels["stable"], "v1.0.0");
+        assert_eq!(all_channels["rc"], "v1.1.0-rc.1");
+        assert!(!all_channels.contains_key("beta"));
+
+        // Check that the given channels take precedence over the topics
+        let topic_channels = github::get_topic_channels(&api, "owner/tool").unwrap();
+        let channels = get_channels(vec!["beta".to_string()], topic_channels, "beta");
+        assert_eq!(channels, ["beta"]);
+    }
+
+    #[test]
+    fn test_group_tags_by_channel() {
+        let tags = vec![
+            "v1.0.0",
+            "v1.2.0-alpha.1",
+            "v1.1.0",
+            "v1.2.0-alpha.2",
+            "v1.1.0-beta.3",
+            "v1.2.0-rc.1",
+            "v1.1.0-rc.1",
+            "other",
+        ];
+        let groups = group_tags_by_channel(&tags, &filter("v", "beta", false)).unwrap();
+
+        assert_eq!(groups["stable"], json!(["v1.1.0", "v1.0.0"]));
+        assert_eq!(groups["alpha"], json!(["v1.2.0-alpha.2", "v1.2.0-alpha.1"]));
+        assert_eq!(groups["beta"], json!(["v1.1.0-beta.3"]));
+        assert_eq!(groups["rc"], json!(["v1.2.0-rc.1", "v1.1.0-rc.1"]));
+        assert_eq!(groups.len(), 4);
+    }
+
+    #[test]
+    fn test_detect_tag_prefix() {
+        let detect = |tags: &[&str], separator| {
+            let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+            detect_tag_prefix(&tags, separator)
+        };
+        assert_eq!(
+            detect(&["v1.0.0", "v1.1.0-rc.1", "latest"], ".").as_deref(),
+            Some("v")
+        );
+        assert_eq!(
+            detect(&["pkg/v1_0_0", "pkg/v2_0_0+build.1"], "_").as_deref(),
+            Some("pkg/v")
+        );
+        assert_eq!(detect(&["1.0.0", "2.0.0"], ".").as_deref(), Some(""));
+        assert_eq!(detect(&["v1.0.0", "2.0.0"], "."), None);
+        assert_eq!(detect(&["latest"], "."), None);
+    }
+
+    #[test]
+    fn test_find_ordering_violations() {
+        let tags = vec!["v1.0.0", "v1.0.0-rc.1", "v1.1.0-rc.1", "v0.9.0"];
+        let filter = filter("v", "rc", true);
+        assert!(
+            find_ordering_violations(&tags, &filter, compare_tags)
+                .unwrap()
+                .is_empty()
+        );
+
+        // A comparator ranking prereleases above stable releases is caught
+        let broken = |a: &str, b: &str, filter: &TagFilter| compare_tags(b, a, filter);
+        assert_eq!(
+            find_ordering_violations(&tags, &filter, broken).unwrap(),
+            ["v1.0.0-rc.1 > v1.0.0"]
+        );
+    }
+
+    #[test]
+    fn test_check_allowed_prereleases() {
+        let allowed = ["alpha", "beta", "rc"].map(String::from);
+        let prerelease_filter = filter("v", "beta", true);
+
+        // Check that a conforming set of tags passes
+        let tags = vec![
+            "v1.0.0",
+            "v1.1.0-alpha.1",
+            "v1.1.0-beta.1",
+            "v1.1.0-rc.1",
+            "x1.0.0-dev.1",
+        ];
+        assert!(check_allowed_prereleases(&tags, &prerelease_filter, &allowed).is_ok());
+
+        // Check that typos in the prerelease identifier are reported
+        let tags = vec!["v1.1.0-rc.1", "v1.1.0-rcc.2", "v1.1.0-bета.1"];
+        let error = check_allowed_prereleases(&tags, &prerelease_filter, &allowed).unwrap_err();
+        assert!(error.to_string().ends_with(": v1.1.0-rcc.2, v1.1.0-bета.1"));
+    }
+
+    #[test]
+    fn test_get_next_prerelease_tag() {
+        let rc_filter = filter("v", "rc", true);
+        let tags = vec![
+            "v1.1.0",
+            "v1.2.0-rc.3",
+            "v1.2.0-rc.10",
+            "v1.2.0-rc.9",
+            "v1.2.0-beta.12",
+            "v1.1.0-rc.2",
+        ];
+
+        // Check that the counter of the latest prerelease is incremented numerically
+        assert_eq!(
+            get_next_prerelease_tag(&tags, &rc_filter, None).unwrap(),
+            "v1.2.0-rc.11"
+        );
+        let target = Version::new(1, 1, 0);
+        assert_eq!(
+            get_next_prerelease_tag(&tags, &rc_filter, Some(&target)).unwrap(),
+            "v1.1.0-rc.3"
+        );
+
+        // Check that a core version without prereleases starts a new series
+        let target = Version::new(1, 3, 0);
+        assert_eq!(
+            get_next_prerelease_tag(&tags, &rc_filter, Some(&target)).unwrap(),
+            "v1.3.0-rc.1"
+        );
+        assert_eq!(
+            get_next_prerelease_tag(&["v1.0.0"], &rc_filter, Some(&target)).unwrap(),
+            "v1.3.0-rc.1"
+        );
+
+        // Check that a target version is needed when there are no prereleases
+        assert!(get_next_prerelease_tag(&["v1.0.0"], &rc_filter, None).is_err());
+    }
+
+    #[test]
+    fn test_get_version_delta() {
+        let delta = |previous: &str, current: &str| {
+            get_version_delta(
+                &Version::parse(previous).unwrap(),
+                &Version::parse(current).unwrap(),
+            )
+        };
+        assert_eq!(delta("1.4.2", "2.0.0"), "major");
+        assert_eq!(delta("1.4.2", "1.5.0"), "minor");
+        assert_eq!(delta("1.4.2", "1.4.3"), "patch");
+        assert_eq!(delta("1.5.0-rc.1", "1.5.0-rc.2"), "prerelease");
+        assert_eq!(delta("1.5.0-rc.2", "1.5.0"), "prerelease");
+        assert_eq!(delta("1.5.0+a", "1.5.0+b"), "none");
+    }
+
+    #[test]
+    fn test_get_nightly_tag() {
+        let stable_filter = filter("v", "rc", false);
+        // 12 March 2024 at 00:00:00 and 23:59:59 UTC
+        for now in [1_710_201_600, 1_710_287_999] {
+            assert_eq!(
+                get_nightly_tag("v1.2.3", &stable_filter, Bump::Patch, now).unwrap(),
+                "v1.2.4-nightly.20240312"
+            );
+        }
+        assert_eq!(
+            get_nightly_tag("v1.2.3-rc.1", &stable_filter, Bump::Minor, 1_710_288_000).unwrap(),
+            "v1.3.0-nightly.20240313"
+        );
+        assert!(get_nightly_tag("vnext", &stable_filter, Bump::Patch, 0).is_none());
+
+        assert_eq!(get_utc_date(0), (1970, 1, 1));
+        assert_eq!(get_utc_date(951_782_400), (2000, 2, 29));
+        assert_eq!(get_utc_date(946_641_600), (1999, 12, 31));
+    }
+
+    #[test]
+    fn test_get_recommended_tag() {
+        let stable_filter = filter("v", "rc", false);
+        let rc_filter = filter("v", "rc", true);
+        let tags = vec!["v1.2.0", "v1.2.0-rc.2", "v1.3.0-rc.4", "v1.3.0-beta.7"];
+
+        // Check that the release branch bumps the latest tag
+        assert_eq!(
+            get_recommended_tag(&tags, &stable_filter, "v1.2.0", Bump::Minor).unwrap(),
+            "v1.3.0"
+        );
+
+        // Check that a prerelease branch continues the unreleased series
+        assert_eq!(
+            get_recommended_tag(&tags, &rc_filter, "v1.3.0-rc.4", Bump::Minor).unwrap(),
+            "v1.3.0-rc.5"
+        );
+
+        // Check that a released series starts over at the bumped stable version
+        let tags = vec!["v1.2.0", "v1.3.0", "v1.3.0-rc.4"];
+        assert_eq!(
+            get_recommended_tag(&tags, &rc_filter, "v1.3.0-rc.4", Bump::Patch).unwrap(),
+            "v1.3.1-rc.1"
+        );
+        assert_eq!(
+            get_recommended_tag(&[], &rc_filter, "v0.0.0", Bump::Minor).unwrap(),
+            "v0.1.0-rc.1"
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let tag_filter = filter("v", "rc", true);
+        let render = |template| render_template(template, "v1.4.2-rc.3+linux", &tag_filter);
+
+        assert_eq!(render("{major}.{minor}").unwrap(), "1.4");
+        assert_eq!(
+            render("release-{major}_{minor}_{patch}").unwrap(),
+            "release-1_4_2"
+        );
+        assert_eq!(render("{prefix}{major}-{prerelease}").unwrap(), "v1-rc.3");
+        assert_eq!(
+            render("{version} ({build})").unwrap(),
+            "1.4.2-rc.3+linux (linux)"
+        );
+        assert_eq!(render("static").unwrap(), "static");
+
+        // Check that empty components render as empty and unknown placeholders are rejected
+        assert_eq!(
+            render_template("{major}-{prerelease}", "v2.0.0", &tag_filter).unwrap(),
+            "2-"
+        );
+        let error = render("{major}.{minr}").unwrap_err();
+        assert!(error.to_string().starts_with("Unknown placeholder {minr}"));
+    }
+
+    #[test]
+    fn test_component_tag_prefix() {
+        let stable_filter = filter("foo/v", "rc", false);
+        let rc_filter = filter("foo/v", "rc", true);
+        let tags = vec![
+            "foo/v1.2.3",
+            "foo/v1.10.0",
+            "foo/v1.11.0-rc.1",
+            "bar/v2.0.0",
+            "foo/1.12.0",
+            "v1.13.0",
+            "foo/vv1.14.0",
+        ];
+
+        // Check that only tags of the component are ordered by version
+        assert_eq!(
+            get_latest_tag(&tags, &stable_filter).unwrap(),
+            "foo/v1.10.0"
+        );
+        assert_eq!(
+            find_previous_tag(&tags, &stable_filter).unwrap(),
+            Some("foo/v1.2.3".to_string())
+        );
+        assert_eq!(
+            get_latest_tag(&tags, &rc_filter).unwrap(),
+            "foo/v1.11.0-rc.1"
+        );
+
+        // Check that the whole prefix is stripped before parsing
+        assert_eq!(
+            parse_tag_version("foo/v1.10.0", &stable_filter),
+            Some(Version::new(1, 10, 0))
+        );
+        assert_eq!(
+            render_template("{prefix} {version}", "foo/v1.10.0", &stable_filter).unwrap(),
+            "foo/v 1.10.0"
+        );
+        assert_eq!(
+            get_next_tag("foo/v1.10.0", &stable_filter, Bump::Minor, false).unwrap(),
+            "foo/v1.11.0"
+        );
+
+        // Check that characters with a meaning in regexes are matched literally
+        let dotted_filter = filter("pkg.core/v", "rc", false);
+        let tags = vec![
+            "pkg.core/v1.0.0",
+            "pkgXcore/v2.0.0",
+            "pkg.core/v1.1.0+linux",
+        ];
+        assert_eq!(
+            get_latest_tag(&tags, &dotted_filter).unwrap(),
+            "pkg.core/v1.1.0+linux"
+        );
+        let plus_filter = filter("c++/", "rc", false);
+        assert_eq!(
+            get_latest_tag(&["c++/1.0.0", "c/1.1.0", "cc/1.2.0"], &plus_filter).unwrap(),
+            "c++/1.0.0"
+        );
+    }
+
+    #[test]
+    fn test_multiple_tag_prefixes() {
+        let stable_filter = TagFilter {
+            tag_prefixes: vec!["v".to_string(), "ver".to_string()],
+            ..filter("", "beta", false)
+        };
+
+        // Check that the highest version wins whichever prefix it uses
+        let tags = vec!["ver1.9.0", "v1.2.0", "ver2.0.0-beta.1", "x3.0.0"];
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "ver1.9.0");
+        let tags = vec!["ver1.9.0", "v1.10.0", "v1.2.0"];
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "v1.10.0");
+        assert_eq!(
+            find_previous_tag(&tags, &stable_filter).unwrap(),
+            Some("ver1.9.0".to_string())
+        );
+
+        // Check that the longest matching prefix is removed
+        assert_eq!(
+            parse_tag_version("ver1.9.0", &stable_filter),
+            Some(Version::new(1, 9, 0))
+        );
+        assert_eq!(
+            render_template("{prefix}", "ver1.9.0", &stable_filter).unwrap(),
+            "ver"
+        );
+
+        // Check that new tags use the first prefix
+        assert_eq!(
+            get_next_tag("ver1.9.0", &stable_filter, Bump::Minor, false).unwrap(),
+            "v1.10.0"
+        );
+    }
+
+    #[test]
+    fn test_find_nonconforming_tags() {
+        let tags = vec![
+            "v1.0.0",
+            "v1.1.0-rc.1",
+            "v1.2.0+linux",
+            "v1.2",
+            "V1.0.0",
+            "v1.0.0-",
+            "vv2.0.0",
+            "v1.0.0.1",
+            "latest",
+            "foo/v1.2",
+            "version-notes",
+        ];
+        assert_eq!(
+            find_nonconforming_tags(&tags, &filter("v", "", false)).unwrap(),
+            vec!["v1.2", "V1.0.0", "v1.0.0-", "vv2.0.0", "v1.0.0.1"]
+        );
+
+        // Check that tags without a prefix may still start with a `v`
+        let tags = vec!["1.0.0", "1.0", "v1.0.0", "release-1"];
+        assert_eq!(
+            find_nonconforming_tags(&tags, &filter("", "", false)).unwrap(),
+            vec!["1.0", "v1.0.0"]
+        );
+    }
+
+    #[test]
+    fn test_find_missing_prereleases() {
+        let rc_filter = filter("v", "rc", true);
+        let tags = vec![
+            "v1.1.0-rc.1",
+            "v1.1.0-rc.3",
+            "v1.1.0-beta.2",
+            "v1.0.0-rc.1",
+            "v1.0.0-rc.2",
+            "v1.0.0",
+            "x2.0.0-rc.2",
+        ];
+        assert_eq!(
+            find_missing_prereleases(&tags, &rc_filter),
+            vec!["v1.1.0-beta.1", "v1.1.0-rc.2"]
+        );
+
+        // Check that contiguous series pass
+        let tags = vec!["v1.0.0-rc.1", "v1.0.0-rc.2", "v1.0.0-rc.3", "v1.1.0-rc.1"];
+        assert!(find_missing_prereleases(&tags, &rc_filter).is_empty());
+    }
+
+    #[test]
+    fn test_constraints() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("constraints.txt");
+        fs::write(&path, "# Supported releases\n>=1.2\n\n<2, !=1.5.0\n").unwrap();
+        let constrained = TagFilter {
+            constraints: read_constraints(&path).unwrap(),
+            ..filter("v", "rc", false)
+        };
+
+        // Check that the known-bad release and versions outside the range are skipped
+        let tags = vec!["v2.0.0", "v1.5.0", "v1.4.2", "v1.1.9"];
+        assert_eq!(get_latest_tag(&tags, &constrained).unwrap(), "v1.4.2");
+        assert_eq!(
+            find_lowest_tag(&tags, &constrained).unwrap(),
+            Some("v1.4.2".to_string())
+        );
+        let tags = vec!["v1.5.0", "v1.5.1+linux", "v1.1.0"];
+        assert_eq!(get_latest_tag(&tags, &constrained).unwrap(), "v1.5.1+linux");
+        assert!(get_latest_tag(&["v1.5.0", "v1.0.0"], &constrained).is_err());
+
+        // Check that invalid requirements are reported with their line
+        fs::write(&path, ">=1.2\n!=1.5\n").unwrap();
+        let error = read_constraints(&path).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid constraint on line 2"), "{error}");
+    }
+
+    #[test]
+    fn test_range_syntax() {
+        let tags = vec![
+            "v1.1.9",
+            "v1.2.0",
+            "v1.2.7",
+            "v1.3.0",
+            "v1.9.4",
+            "v2.0.0",
+            "v1.10.0-rc.1",
+        ];
+        let latest_in = |range: &str, syntax: RangeSyntax| {
+            let filter = TagFilter {
+                constraints: parse_range(range, syntax).unwrap(),
+                ..filter("v", "rc", false)
+            };
+            get_latest_tag(&tags, &filter).unwrap()
+        };
+
+        // A caret allows minor and patch updates, a tilde only patch updates
+        assert_eq!(latest_in("^1.2.0", RangeSyntax::Npm), "v1.9.4");
+        assert_eq!(latest_in("~1.2.0", RangeSyntax::Npm), "v1.2.7");
+        // A bare npm version is an exact match, unlike a bare Cargo version
+        assert_eq!(latest_in("1.2.0", RangeSyntax::Npm), "v1.2.0");
+        assert_eq!(latest_in("1.2.0", RangeSyntax::Cargo), "v1.9.4");
+        assert_eq!(latest_in("~1.2.0 || 1.3.x", RangeSyntax::Npm), "v1.3.0");
+        assert_eq!(latest_in(">=1.2,<1.9", RangeSyntax::Python), "v1.3.0");
+        assert_eq!(latest_in("~=1.2.0", RangeSyntax::Python), "v1.2.7");
+
+        let error = parse_range("^one", RangeSyntax::Npm).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid range ^one"));
+    }
+
+    #[test]
+    fn test_count_prerelease_channels() {
+        let tags = vec![
+            "v1.0.0",
+            "v1.1.0-alpha.1",
+            "v1.1.0-alpha.2",
+            "v1.1.0-beta.1",
+            "v1.1.0-rc.1",
+            "v1.1.0-rc.2",
+            "v1.1.0-rc.3+build.5",
+            "x1.2.0-dev.1",
+        ];
+        let channels = count_prerelease_channels(&tags, &filter("v", "", false));
+        assert_eq!(
+            channels.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("alpha".to_string(), 2),
+                ("beta".to_string(), 1),
+                ("rc".to_string(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_release_lines() {
+        let tags = vec![
+            "v0.9.0",
+            "v1.0.0",
+            "v1.0.1",
+            "v1.1.0",
+            "v1.2.0+build.3",
+            "v2.0.0",
+            "v2.0.5",
+            "v3.0.0-rc.1",
+            "x4.0.0",
+        ];
+        assert_eq!(
+            count_release_lines(&tags, &filter("v", "rc", false)).unwrap(),
+            (3, 5)
+        );
+        assert_eq!(
+            count_release_lines(&[], &filter("v", "rc", false)).unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_count_prerelease_iterations() {
+        let tags = vec![
+            "v1.2.0-rc.1",
+            "v1.3.0-beta.1",
+            "v1.3.0-rc.1",
+            "v1.3.0-rc.2",
+            "v1.3.0-rc.3",
+            "v1.3.0",
+            "v1.4.0-rc.1",
+        ];
+        let rc_filter = filter("v", "rc", false);
+        let count = |version| {
+            count_prerelease_iterations(&tags, &rc_filter, &Version::parse(version).unwrap())
+                .unwrap()
+        };
+        assert_eq!(count("1.3.0"), 3);
+        assert_eq!(count("1.3.0-rc.2"), 3);
+        assert_eq!(count("1.4.0-rc.1"), 1);
+        assert_eq!(count("1.5.0"), 0);
+    }
+
+    #[test]
+    fn test_get_branch_mode() {
+        let rules = BranchRules {
+            stable: vec![
+                Pattern::new("release/*").unwrap(),
+                Pattern::new("hotfix-*").unwrap(),
+            ],
+            prerelease: vec![
+                Pattern::new("feature/*").unwrap(),
+                Pattern::new("release/*-rc").unwrap(),
+            ],
+            default_mode: None,
+        };
+        let mode = |branch| get_branch_mode(branch, "main", &rules).0;
+
+        // Check that each list forces its mode, with stable patterns checked first
+        assert_eq!(mode("release/1.0"), BranchMode::Stable);
+        assert_eq!(mode("hotfix-login"), BranchMode::Stable);
+        assert_eq!(mode("release/1.0-rc"), BranchMode::Stable);
+        assert_eq!(mode("feature/login"), BranchMode::Prerelease);
+
+        // Check that other branches fall through to the release branch comparison
+        assert_eq!(mode("main"), BranchMode::Stable);
+        assert_eq!(mode("develop"), BranchMode::Prerelease);
+
+        // Check that the default mode applies to branches matching neither list
+        let rules = BranchRules {
+            default_mode: Some(BranchMode::Stable),
+            ..rules
+        };
+        assert_eq!(
+            get_branch_mode("develop", "main", &rules).0,
+            BranchMode::Stable
+        );
+        assert_eq!(
+            get_branch_mode("feature/login", "main", &rules).0,
+            BranchMode::Prerelease
+        );
+        let rules = BranchRules {
+            default_mode: Some(BranchMode::Prerelease),
+            ..rules
+        };
+        assert_eq!(
+            get_branch_mode("develop", "main", &rules).0,
+            BranchMode::Prerelease
+        );
+        assert_eq!(
+            get_branch_mode("main", "main", &rules).0,
+            BranchMode::Stable
+        );
+    }
+
+    #[test]
+    fn test_resolve_branch_name() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        // Check that a normal branch shorthand is used without consulting the environment
+        let branch = resolve_branch_name(Some("main"), env(&[("GITHUB_HEAD_REF", "feature")]));
+        assert_eq!(branch.as_deref(), Some("main"));
+
+        // Check that the literal HEAD shorthand triggers the fallback
+        let branch = resolve_branch_name(
+            Some("HEAD"),
+            env(&[("GITHUB_HEAD_REF", ""), ("GITHUB_REF", "refs/heads/main")]),
+        );
+        assert_eq!(branch.as_deref(), Some("main"));
+        let branch = resolve_branch_name(Some("HEAD"), env(&[("GITHUB_HEAD_REF", "feature")]));
+        assert_eq!(branch.as_deref(), Some("feature"));
+        let branch = resolve_branch_name(None, env(&[("GITHUB_REF", "refs/heads/release")]));
+        assert_eq!(branch.as_deref(), Some("release"));
+
+        // Check that a tag ref or an empty environment does not name a branch
+        let branch = resolve_branch_name(Some("HEAD"), env(&[("GITHUB_REF", "refs/tags/v1.0.0")]));
+        assert_eq!(branch, None);
+        assert_eq!(resolve_branch_name(Some("HEAD"), env(&[])), None);
+    }
+
+    #[test]
+    fn test_parse_repo_slug() {
+        let slug = Some("owner/repo".to_string());
+        assert_eq!(parse_repo_slug("git@github.com:owner/repo.git"), slug);
+        assert_eq!(parse_repo_slug("https://github.com/owner/repo"), slug);
+        assert_eq!(parse_repo_slug("https://github.com/owner/repo.git/"), slug);
+        assert_eq!(
+            parse_repo_slug("ssh://git@github.com:22/owner/repo.git"),
+            slug
+        );
+        assert_eq!(parse_repo_slug("https://token@github.com/owner/repo"), slug);
+
+        // Check that a URL without an owner has no slug
+        assert_eq!(parse_repo_slug("https://github.com/repo"), None);
+        assert_eq!(parse_repo_slug("/srv/git/repo.git"), None);
+    }
+
+    #[test]
+    fn test_get_branch_suffix() {
+        assert_eq!(
+            get_branch_suffix("feature/login").as_deref(),
+            Some("feature-login")
+        );
+        assert_eq!(
+            get_branch_suffix("Fix/ISSUE_42--retry").as_deref(),
+            Some("fix-issue-42-retry")
+        );
+        assert_eq!(
+            get_branch_suffix("/dependabot/").as_deref(),
+            Some("dependabot")
+        );
+        assert_eq!(get_branch_suffix("_/_"), None);
+    }
+
+    #[test]
+    fn test_is_first_release() {
+        let stable_filter = filter("v", "beta", false);
 
-        // Test with different prerelease suffixes
-        let tags = vec!["v1.0.0-beta.1", "v1.0.0-beta.2"];
-        let latest_tag = get_latest_tag(tags, "v", "beta", true).unwrap();
-        assert_eq!(latest_tag, "v1.0.0-beta.2");
+        // Check that a single stable tag is the first release, even with prereleases around
+        let tags = vec!["v1.0.0", "v1.1.0-beta.1"];
+        assert!(is_first_release(&tags, &stable_filter, "v1.0.0", None).unwrap());
 
-        // Test with multi-digit prerelease numbers (check that lexical comparison is not used)
-        let tags = vec!["v1.0.0-beta.10", "v1.0.0-beta.2"];
-        let latest_tag = get_latest_tag(tags, "v", "beta", true).unwrap();
-        assert_eq!(latest_tag, "v1.0.0-beta.10");
+        // Check that multiple stable tags are not the first release
+        let tags = vec!["v1.0.0", "v1.1.0"];
+        assert!(!is_first_release(&tags, &stable_filter, "v1.1.0", None).unwrap());
 
-        // Check that no matching tags returns an error
+        // Check that falling back to the default tag counts as the first release
         let tags: Vec<&str> = vec![];
-        let result = get_latest_tag(tags, "v", "beta", false);
-        assert!(result.is_err());
+        let latest_tag = find_latest_tag(&tags, &stable_filter)
+            .unwrap()
+            .unwrap_or("v0.0.0".to_string());
+        assert!(is_first_release(&tags, &stable_filter, &latest_tag, Some("v0.0.0")).unwrap());
+        assert!(!is_first_release(&tags, &stable_filter, &latest_tag, None).unwrap());
     }
 }
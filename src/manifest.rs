@@ -0,0 +1,127 @@
+//! Reading the version declared in a package manifest
+
+use semver::Version;
+use serde_json::Value;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Read the version declared in a package manifest
+///
+/// `.json` files are read as a `package.json` and `.toml` files as a `Cargo.toml` or
+/// `pyproject.toml`, taking the first of `package.version`, `workspace.package.version`,
+/// `project.version` and `tool.poetry.version` that is set.
+/// # Arguments
+/// * `path` - The path of the manifest
+/// # Returns
+/// The declared version
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, has an unsupported extension, or does
+/// not declare a valid semantic version
+pub fn read_manifest_version(path: &Path) -> Result<Version, Box<dyn Error>> {
+    let contents = read_to_string(path)
+        .map_err(|e| format!("Failed to read the manifest {}: {e}", path.display()))?;
+    let version = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => json_version(&contents)?,
+        Some("toml") => toml_version(&contents)?,
+        _ => {
+            return Err(format!(
+                "Unsupported manifest {}, expected a .json or .toml file",
+                path.display()
+            )
+            .into());
+        }
+    };
+
+    let version = version.ok_or_else(|| format!("No version found in {}", path.display()))?;
+    Version::parse(&version).map_err(|e| {
+        format!(
+            "The version in {} ({version}) is not a valid semantic version: {e}",
+            path.display()
+        )
+        .into()
+    })
+}
+
+/// Get the `version` field of a `package.json`
+fn json_version(contents: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let manifest: Value = serde_json::from_str(contents)?;
+    Ok(manifest["version"].as_str().map(str::to_string))
+}
+
+/// Get the version of a `Cargo.toml` or `pyproject.toml`
+fn toml_version(contents: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let manifest = contents.parse::<toml::Table>()?;
+    let version = [
+        &["package", "version"][..],
+        &["workspace", "package", "version"],
+        &["project", "version"],
+        &["tool", "poetry", "version"],
+    ]
+    .iter()
+    .find_map(|keys| {
+        let (last, tables) = keys.split_last()?;
+        let table = tables
+            .iter()
+            .try_fold(&manifest, |table, key| table.get(*key)?.as_table())?;
+        table.get(*last)?.as_str()
+    });
+    Ok(version.map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+    use tempfile::TempDir;
+
+    /// Write a manifest into a temporary directory and read its version
+    fn version_of(file_name: &str, contents: &str) -> Result<Version, Box<dyn Error>> {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(file_name);
+        write(&path, contents).unwrap();
+        read_manifest_version(&path)
+    }
+
+    #[test]
+    fn test_read_manifest_version() {
+        let version = version_of(
+            "Cargo.toml",
+            "[package]\nname = \"tool\"\nversion = \"1.2.3\"\n\n[dependencies.serde]\nversion = \"1.0.0\"\n",
+        );
+        assert_eq!(version.unwrap(), Version::new(1, 2, 3));
+
+        let version = version_of(
+            "Cargo.toml",
+            "[workspace.package]\nversion = \"0.4.0-rc.1\"\n",
+        );
+        assert_eq!(version.unwrap(), Version::parse("0.4.0-rc.1").unwrap());
+
+        let version = version_of(
+            "package.json",
+            "{\"name\": \"tool\", \"version\": \"2.0.1\"}",
+        );
+        assert_eq!(version.unwrap(), Version::new(2, 0, 1));
+
+        let version = version_of(
+            "pyproject.toml",
+            "[project]\nname = \"tool\"\nversion = \"3.1.0\"\n",
+        );
+        assert_eq!(version.unwrap(), Version::new(3, 1, 0));
+
+        let version = version_of("pyproject.toml", "[tool.poetry]\nversion = \"3.2.0\"\n");
+        assert_eq!(version.unwrap(), Version::new(3, 2, 0));
+
+        // Check that missing, invalid and unsupported versions are reported
+        let error = version_of("pyproject.toml", "[project]\ndynamic = [\"version\"]\n");
+        assert!(
+            error
+                .unwrap_err()
+                .to_string()
+                .starts_with("No version found")
+        );
+        let error = version_of("package.json", "{\"version\": \"next\"}");
+        assert!(error.unwrap_err().to_string().contains("(next)"));
+        assert!(version_of("setup.cfg", "[metadata]\nversion = 1.0.0\n").is_err());
+    }
+}
@@ -0,0 +1,413 @@
+//! Writing the results of a run as GitHub actions outputs, GitLab CI dotenv reports or JSON
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::error::Error;
+
+use std::path::{Path, PathBuf};
+
+use std::fs::{OpenOptions, write};
+use std::io::Write;
+/// The version of the shape of the JSON output, bumped when outputs are renamed, removed or
+/// change type (adding outputs is compatible)
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// The formats the outputs can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// `name=value` lines for the GitHub actions output file
+    Github,
+    /// A single JSON object
+    Json,
+    /// `NAME=value` lines for a GitLab CI `artifacts:reports:dotenv` file
+    GitlabDotenv,
+}
+
+/// The named results of a run, kept in insertion order
+#[derive(Debug, Default)]
+pub struct Outputs {
+    values: Vec<(String, Value)>,
+    prefix: String,
+}
+
+impl Outputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write every output name with a prefix
+    /// # Arguments
+    /// * `prefix` - The text put before every output name (e.g. `linux_`)
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Set an output, replacing any previous value with the same name
+    /// # Arguments
+    /// * `name` - The name of the output
+    /// * `value` - The value of the output
+    pub fn insert(&mut self, name: &str, value: impl Into<Value>) {
+        let value = value.into();
+        match self
+            .values
+            .iter_mut()
+            .find(|(existing, _)| existing == name)
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((name.to_string(), value)),
+        }
+    }
+
+    /// Write the outputs to a file
+    ///
+    /// The github format is appended, as the GitHub actions output file is shared by every step
+    /// of a job and already holds the outputs written before this run. The other formats
+    /// replace the file.
+    /// # Arguments
+    /// * `path` - The path of the output file
+    /// * `format` - The format to write the outputs in
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn write(&self, path: &Path, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+        match format {
+            OutputFormat::Github => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                file.write_all(self.to_github().as_bytes())?;
+            }
+            OutputFormat::Json => write(path, self.to_json())?,
+            OutputFormat::GitlabDotenv => write(path, self.to_gitlab_dotenv())?,
+        }
+        Ok(())
+    }
+
+    /// Write the outputs to several files, each in its own format
+    /// # Arguments
+    /// * `targets` - The paths of the files and the formats to write them in
+    /// # Errors
+    /// Returns an error if a file cannot be written
+    pub fn write_all(&self, targets: &[(PathBuf, OutputFormat)]) -> Result<(), Box<dyn Error>> {
+        for (path, format) in targets {
+            self.write(path, *format)?;
+        }
+        Ok(())
+    }
+
+    /// Iterate over the outputs with the prefix added to their names
+    fn prefixed(&self) -> impl Iterator<Item = (String, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (format!("{}{name}", self.prefix), value))
+    }
+
+    /// Render the outputs in the GitHub actions output file syntax
+    fn to_github(&self) -> String {
+        self.prefixed()
+            .map(|(name, value)| {
+                let value = github_value(value);
+                // Multiline values must use the heredoc form
+                if value.contains(['\n', '\r']) {
+                    let delimiter = heredoc_delimiter(&value);
+                    format!("{name}<<{delimiter}\n{value}\n{delimiter}\n")
+                } else {
+                    format!("{name}={value}\n")
+                }
+            })
+            .collect()
+    }
+
+    /// Render the outputs in the GitLab CI dotenv report syntax
+    ///
+    /// GitLab keeps quotes as part of the value and rejects multiline values, so values are
+    /// written unquoted on a single line.
+    fn to_gitlab_dotenv(&self) -> String {
+        self.prefixed()
+            .map(|(name, value)| format!("{}={}\n", dotenv_key(&name), dotenv_value(value)))
+            .collect()
+    }
+
+    /// Render the outputs as a JSON object, starting with the `schema_version` of the object
+    fn to_json(&self) -> String {
+        let mut object = Map::new();
+        object.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+        object.extend(self.prefixed().map(|(name, value)| (name, value.clone())));
+        format!("{}\n", Value::Object(object))
+    }
+}
+
+/// Work out which file each requested format is written to
+///
+/// A single format is written to the output file if one is given and to the GitHub actions
+/// output file otherwise. With several formats, the github format always goes to the GitHub
+/// actions output file and every other format takes the next output file in order.
+/// # Arguments
+/// * `formats` - The requested formats; repeated formats are written once
+/// * `output_files` - The paths given with `--output-file`
+/// * `github_output` - The path of the GitHub actions output file, if it is set
+/// # Returns
+/// The path and format of every file to write
+/// # Errors
+/// Returns an error if the number of output files does not match the formats or the GitHub
+/// actions output file is needed but not set
+pub fn get_output_targets(
+    formats: &[OutputFormat],
+    output_files: &[PathBuf],
+    github_output: Option<PathBuf>,
+) -> Result<Vec<(PathBuf, OutputFormat)>, Box<dyn Error>> {
+    let mut unique_formats = Vec::new();
+    for format in formats {
+        if !unique_formats.contains(format) {
+            unique_formats.push(*format);
+        }
+    }
+    let github_output = || {
+        github_output
+            .clone()
+            .ok_or("GITHUB_OUTPUT environment variable missing.")
+    };
+
+    if let [format] = unique_formats[..] {
+        return match output_files {
+            [] => Ok(vec![(github_output()?, format)]),
+            [output_file] => Ok(vec![(output_file.clone(), format)]),
+            _ => Err("Only one output file can be given for a single format".into()),
+        };
+    }
+
+    let file_formats = unique_formats
+        .iter()
+        .filter(|format| **format != OutputFormat::Github)
+        .count();
+    if output_files.len() != file_formats {
+        return Err(format!(
+            "{file_formats} output files are needed for the formats other than github, but {} were given",
+            output_files.len()
+        )
+        .into());
+    }
+    let mut output_files = output_files.iter();
+    unique_formats
+        .into_iter()
+        .map(|format| match format {
+            OutputFormat::Github => Ok((github_output()?, format)),
+            // There is one output file for every format other than github, checked above
+            _ => Ok((output_files.next().cloned().unwrap_or_default(), format)),
+        })
+        .collect()
+}
+
+/// Convert an output value into the plain text GitHub actions expects
+///
+/// Strings are written as-is, null becomes empty, lists of strings are written one per line
+/// and anything else is written as JSON.
+fn github_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        Value::Array(items) if items.iter().all(Value::is_string) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        value => value.to_string(),
+    }
+}
+
+/// Choose a heredoc delimiter that does not occur in a value
+///
+/// A value containing the delimiter could end the heredoc early and inject further outputs, so
+/// `EOF` is suffixed with a counter until it no longer occurs.
+fn heredoc_delimiter(value: &str) -> String {
+    let mut delimiter = "EOF".to_string();
+    let mut counter = 0;
+    while value.contains(&delimiter) {
+        counter += 1;
+        delimiter = format!("EOF_{counter}");
+    }
+    delimiter
+}
+
+/// Format a GitHub actions workflow command that annotates the run (e.g. `::error::message`)
+///
+/// The message is escaped so line breaks and `%` survive and cannot end the command early.
+/// # Arguments
+/// * `command` - The annotation level: `error`, `warning` or `notice`
+/// * `message` - The text of the annotation
+pub fn format_annotation(command: &str, message: &str) -> String {
+    let message = message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    format!("::{command}::{message}")
+}
+
+/// Convert an output name into a valid dotenv variable name
+///
+/// Only letters, digits and underscores are allowed, and the name must not start with a digit.
+fn dotenv_key(name: &str) -> String {
+    let key = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if key.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{key}")
+    } else {
+        key
+    }
+}
+
+/// Convert an output value into single line text for a dotenv report
+///
+/// Lists of strings are comma separated and any other line breaks are replaced by spaces.
+fn dotenv_value(value: &Value) -> String {
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_string) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => github_value(value).replace(['\r', '\n'], " "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_github_format() {
+        let mut outputs = Outputs::new();
+        outputs.insert("latest_tag", "v1.0.0");
+        outputs.insert("missing", Value::Null);
+        outputs.insert("exists", true);
+        outputs.insert("tags", json!(["1", "1.0"]));
+        outputs.insert("channels", json!({"stable": "v1.0.0"}));
+
+        assert_eq!(
+            outputs.to_github(),
+            "latest_tag=v1.0.0\nmissing=\nexists=true\ntags<<EOF\n1\n1.0\nEOF\nchannels={\"stable\":\"v1.0.0\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_github_heredoc_delimiter() {
+        let mut outputs = Outputs::new();
+        outputs.insert("message", "Release notes\nEOF\nlatest_tag=v9.9.9");
+        outputs.insert("notes", "EOF_1 and EOF\nsecond line");
+        outputs.insert("latest_tag", "v1.0.0");
+
+        assert_eq!(
+            outputs.to_github(),
+            "message<<EOF_1\nRelease notes\nEOF\nlatest_tag=v9.9.9\nEOF_1\nnotes<<EOF_2\nEOF_1 and EOF\nsecond line\nEOF_2\nlatest_tag=v1.0.0\n"
+        );
+    }
+
+    #[test]
+    fn test_gitlab_dotenv_format() {
+        let mut outputs = Outputs::new();
+        outputs.insert("latest_tag", "v1.0.0");
+        outputs.insert("missing", Value::Null);
+        outputs.insert("exists", true);
+        outputs.insert("docker_tags", json!(["1", "1.0", "latest"]));
+        outputs.insert("latest_tag_message", "Release 1.0.0\n\nFirst release");
+        outputs.insert("2nd-tag", "v0.9.0");
+
+        assert_eq!(
+            outputs.to_gitlab_dotenv(),
+            "latest_tag=v1.0.0\nmissing=\nexists=true\ndocker_tags=1,1.0,latest\nlatest_tag_message=Release 1.0.0  First release\n_2nd_tag=v0.9.0\n"
+        );
+    }
+
+    #[test]
+    fn test_get_output_targets() {
+        let github_output = Some(PathBuf::from("github_output"));
+        let json_file = PathBuf::from("outputs.json");
+
+        assert_eq!(
+            get_output_targets(&[OutputFormat::Json], &[], github_output.clone()).unwrap(),
+            [(PathBuf::from("github_output"), OutputFormat::Json)]
+        );
+        assert_eq!(
+            get_output_targets(
+                &[
+                    OutputFormat::Github,
+                    OutputFormat::Json,
+                    OutputFormat::Github
+                ],
+                std::slice::from_ref(&json_file),
+                github_output.clone()
+            )
+            .unwrap(),
+            [
+                (PathBuf::from("github_output"), OutputFormat::Github),
+                (json_file.clone(), OutputFormat::Json)
+            ]
+        );
+
+        // Every format other than github needs its own file
+        assert!(
+            get_output_targets(
+                &[OutputFormat::Json, OutputFormat::GitlabDotenv],
+                std::slice::from_ref(&json_file),
+                github_output
+            )
+            .is_err()
+        );
+        assert!(
+            get_output_targets(
+                &[OutputFormat::Github, OutputFormat::Json],
+                &[json_file],
+                None
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_format_annotation() {
+        assert_eq!(
+            format_annotation("error", "Tags v1.0.0-rcc.1 are not allowed"),
+            "::error::Tags v1.0.0-rcc.1 are not allowed"
+        );
+        assert_eq!(
+            format_annotation("warning", "100% done\nnext line"),
+            "::warning::100%25 done%0Anext line"
+        );
+    }
+
+    #[test]
+    fn test_output_prefix() {
+        let mut outputs = Outputs::new().with_prefix("linux_");
+        outputs.insert("latest_tag", "v1.0.0");
+        outputs.insert("docker_tags", json!(["1", "latest"]));
+        outputs.insert("latest_tag", "v1.1.0");
+
+        assert_eq!(
+            outputs.to_github(),
+            "linux_latest_tag=v1.1.0\nlinux_docker_tags<<EOF\n1\nlatest\nEOF\n"
+        );
+        assert_eq!(
+            outputs.to_json(),
+            "{\"schema_version\":1,\"linux_latest_tag\":\"v1.1.0\",\"linux_docker_tags\":[\"1\",\"latest\"]}\n"
+        );
+        let mut outputs = Outputs::new().with_prefix("arm-64.");
+        outputs.insert("latest_tag", "v1.0.0");
+        assert_eq!(outputs.to_gitlab_dotenv(), "arm_64_latest_tag=v1.0.0\n");
+    }
+
+    #[test]
+    fn test_json_format() {
+        let mut outputs = Outputs::new();
+        outputs.insert("latest_tag", "v1.0.0");
+        outputs.insert("message", Value::Null);
+        outputs.insert("latest_tag", "v1.1.0");
+
+        assert_eq!(
+            outputs.to_json(),
+            "{\"schema_version\":1,\"latest_tag\":\"v1.1.0\",\"message\":null}\n"
+        );
+        let json: Value = serde_json::from_str(&outputs.to_json()).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    }
+}
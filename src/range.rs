@@ -0,0 +1,244 @@
+//! Translating npm and Python version ranges into Cargo version requirements
+
+use clap::ValueEnum;
+use std::error::Error;
+
+/// The syntaxes a version range can be written in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RangeSyntax {
+    /// Cargo requirements such as `>=1.2, <2` or `^1.2`
+    #[default]
+    Cargo,
+    /// npm ranges such as `^1.2.0`, `~1.2.0`, `>=1.2.0 <2.0.0`, `1.2.x` or `1.0.0 - 1.4.0`, with
+    /// `||` between alternatives
+    Npm,
+    /// Python (PEP 440) specifiers such as `>=1.2,<2`, `~=1.2` or `==1.2.*`
+    Python,
+}
+
+/// The characters an npm comparator operator is made of
+const NPM_OPERATOR_CHARS: [char; 5] = ['<', '>', '=', '~', '^'];
+
+/// The operators of Python specifiers, longest first so `===` is not read as `==`
+const PYTHON_OPERATORS: [&str; 8] = ["===", "~=", "==", "!=", "<=", ">=", "<", ">"];
+
+/// Translate a version range into Cargo requirements
+/// # Arguments
+/// * `range` - The range (e.g. `^1.2.0` in npm syntax)
+/// * `syntax` - The syntax the range is written in
+/// # Returns
+/// The alternatives of the range, any of which a version may satisfy, each as Cargo requirements
+/// separated by commas where `!=` excludes a single version (e.g. `>=1.2.0, <2.0.0`)
+/// # Errors
+/// Returns an error if the range uses a form that has no Cargo equivalent
+pub fn translate_range(range: &str, syntax: RangeSyntax) -> Result<Vec<String>, Box<dyn Error>> {
+    match syntax {
+        RangeSyntax::Cargo => Ok(vec![range.trim().to_string()]),
+        RangeSyntax::Npm => range.split("||").map(translate_npm_set).collect(),
+        RangeSyntax::Python => Ok(vec![translate_python(range)?]),
+    }
+}
+
+/// Translate one set of npm comparators, all of which must match
+///
+/// A bare version is an exact match in npm but a caret requirement in Cargo, so it is given an
+/// `=` operator, which in Cargo also matches partial versions such as `1.2` like npm does.
+fn translate_npm_set(set: &str) -> Result<String, Box<dyn Error>> {
+    let tokens = set.split_whitespace().collect::<Vec<_>>();
+    if let [lower, "-", upper] = tokens[..] {
+        let bounds = [(">=", lower), ("<=", upper)]
+            .into_iter()
+            .filter_map(|(operator, version)| Some(format!("{operator}{}", npm_version(version)?)))
+            .collect::<Vec<_>>();
+        return Ok(if bounds.is_empty() {
+            "*".to_string()
+        } else {
+            bounds.join(", ")
+        });
+    }
+
+    // npm allows a space between an operator and its version (e.g. `>= 1.2.0`)
+    let mut comparators = Vec::new();
+    let mut pending_operator = None;
+    for token in tokens {
+        if token.chars().all(|c| NPM_OPERATOR_CHARS.contains(&c)) {
+            pending_operator = Some(token);
+            continue;
+        }
+        let comparator = match pending_operator.take() {
+            Some(operator) => format!("{operator}{token}"),
+            None => token.to_string(),
+        };
+        comparators.push(translate_npm_comparator(&comparator)?);
+    }
+    if let Some(operator) = pending_operator {
+        return Err(format!("The npm range {set:?} ends with the operator {operator}").into());
+    }
+
+    if comparators.is_empty() {
+        return Ok("*".to_string());
+    }
+    Ok(comparators.join(", "))
+}
+
+/// Translate a single npm comparator (e.g. `^1.2.0`, `v1.2.3` or `1.x`)
+fn translate_npm_comparator(comparator: &str) -> Result<String, Box<dyn Error>> {
+    let version_start = comparator
+        .find(|c| !NPM_OPERATOR_CHARS.contains(&c))
+        .unwrap_or(comparator.len());
+    let (operator, version) = comparator.split_at(version_start);
+    let Some(version) = npm_version(version) else {
+        // A wildcard matches every version, whatever the operator
+        return Ok("*".to_string());
+    };
+    let operator = match operator {
+        "" => "=",
+        "^" | "~" | "=" | "<" | "<=" | ">" | ">=" => operator,
+        _ => return Err(format!("Unsupported npm comparator {comparator}").into()),
+    };
+    Ok(format!("{operator}{version}"))
+}
+
+/// Remove the `v` and any wildcard components from an npm version (e.g. `v1.2.x` becomes `1.2`)
+/// # Returns
+/// The version, or `None` if it is a wildcard for every version (e.g. `*` or `x`)
+fn npm_version(version: &str) -> Option<String> {
+    let version = version
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .take_while(|component| !matches!(*component, "x" | "X" | "*"))
+        .collect::<Vec<_>>()
+        .join(".");
+    (!version.is_empty()).then_some(version)
+}
+
+/// Translate comma separated Python specifiers, all of which must match
+fn translate_python(specifiers: &str) -> Result<String, Box<dyn Error>> {
+    let requirements = specifiers
+        .split(',')
+        .map(str::trim)
+        .filter(|specifier| !specifier.is_empty())
+        .map(translate_python_specifier)
+        .collect::<Result<Vec<_>, _>>()?;
+    if requirements.is_empty() {
+        return Ok("*".to_string());
+    }
+    Ok(requirements.join(", "))
+}
+
+/// Translate a single Python specifier (e.g. `~=1.2` or `==1.2.*`)
+///
+/// Python pads partial versions with zeros (`<=1.2` is `<=1.2.0`), while Cargo reads them as
+/// every version starting with them, so versions are padded to three components.
+fn translate_python_specifier(specifier: &str) -> Result<String, Box<dyn Error>> {
+    let operator = PYTHON_OPERATORS
+        .iter()
+        .find(|operator| specifier.starts_with(*operator))
+        .ok_or_else(|| format!("The Python specifier {specifier} has no operator"))?;
+    let version = specifier[operator.len()..]
+        .trim()
+        .trim_start_matches(['v', 'V']);
+
+    match *operator {
+        "==" if version.ends_with(".*") => Ok(format!("={}", version.trim_end_matches(".*"))),
+        "!=" if version.ends_with(".*") => Err(format!(
+            "The Python specifier {specifier} excludes a wildcard, which is not supported"
+        )
+        .into()),
+        "==" | "===" => Ok(format!("={}", pad_version(version))),
+        "~=" => {
+            // `~=1.2` is `>=1.2, ==1.*` and `~=1.2.3` is `>=1.2.3, ==1.2.*`
+            let components = version
+                .split('.')
+                .map(str::parse::<u64>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("The Python specifier {specifier} needs a release version"))?;
+            let [kept @ .., last, _] = components.as_slice() else {
+                return Err(format!(
+                    "The Python specifier {specifier} needs at least two components"
+                )
+                .into());
+            };
+            let upper = kept
+                .iter()
+                .copied()
+                .chain([last + 1])
+                .map(|component| component.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            Ok(format!(
+                ">={}, <{}",
+                pad_version(version),
+                pad_version(&upper)
+            ))
+        }
+        operator => Ok(format!("{operator}{}", pad_version(version))),
+    }
+}
+
+/// Pad a release version to three components (e.g. `1.2` becomes `1.2.0`)
+fn pad_version(version: &str) -> String {
+    if version.contains(['-', '+']) {
+        return version.to_string();
+    }
+    let mut components = version.split('.').collect::<Vec<_>>();
+    components.resize(components.len().max(3), "0");
+    components.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(range: &str, syntax: RangeSyntax) -> Vec<String> {
+        translate_range(range, syntax).unwrap()
+    }
+
+    #[test]
+    fn test_translate_npm_range() {
+        assert_eq!(translate("^1.2.0", RangeSyntax::Npm), ["^1.2.0"]);
+        assert_eq!(translate("~1.2.0", RangeSyntax::Npm), ["~1.2.0"]);
+        assert_eq!(translate("1.2.3", RangeSyntax::Npm), ["=1.2.3"]);
+        assert_eq!(translate("v1.2.x", RangeSyntax::Npm), ["=1.2"]);
+        assert_eq!(translate("*", RangeSyntax::Npm), ["*"]);
+        assert_eq!(
+            translate(">= 1.2.0 <2.0.0", RangeSyntax::Npm),
+            [">=1.2.0, <2.0.0"]
+        );
+        assert_eq!(
+            translate("1.0.0 - 1.4", RangeSyntax::Npm),
+            [">=1.0.0, <=1.4"]
+        );
+        assert_eq!(
+            translate("^1.0.0 || ~2.1.0", RangeSyntax::Npm),
+            ["^1.0.0", "~2.1.0"]
+        );
+        assert!(translate_range(">=", RangeSyntax::Npm).is_err());
+    }
+
+    #[test]
+    fn test_translate_python_range() {
+        assert_eq!(
+            translate(">=1.2,<2", RangeSyntax::Python),
+            [">=1.2.0, <2.0.0"]
+        );
+        assert_eq!(translate("~=1.2", RangeSyntax::Python), [">=1.2.0, <2.0.0"]);
+        assert_eq!(
+            translate("~=1.2.3", RangeSyntax::Python),
+            [">=1.2.3, <1.3.0"]
+        );
+        assert_eq!(translate("==1.2.*", RangeSyntax::Python), ["=1.2"]);
+        assert_eq!(
+            translate("==1.2, !=1.2.0", RangeSyntax::Python),
+            ["=1.2.0, !=1.2.0"]
+        );
+        assert!(translate_range("~=1", RangeSyntax::Python).is_err());
+        assert!(translate_range("!=1.2.*", RangeSyntax::Python).is_err());
+        assert!(translate_range("1.2", RangeSyntax::Python).is_err());
+    }
+
+    #[test]
+    fn test_translate_cargo_range() {
+        assert_eq!(translate(" >=1.2, <2 ", RangeSyntax::Cargo), [">=1.2, <2"]);
+    }
+}
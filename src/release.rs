@@ -0,0 +1,206 @@
+//! Building the outputs that describe a release: its Docker tags, its metadata for release
+//! note generators and the tags it follows
+
+use crate::bump::{Bump, get_next_tag};
+use crate::history::{get_commits_since, is_head_on_tag};
+use crate::output::SCHEMA_VERSION;
+use crate::selection::{
+    TagFilter, compare_tags, find_latest_tag, find_previous_tag, get_channel, get_matching_tags,
+    parse_tag_version,
+};
+use clap::ValueEnum;
+use git2::Repository;
+use serde_json::{Value, json};
+use std::error::Error;
+
+/// Derive the Docker image tags for a selected tag
+///
+/// A stable release such as `v1.4.2` yields the rolling tags `1`, `1.4`, `1.4.2` and
+/// `latest`. A prerelease only yields its full version and a moving tag named after the
+/// channel of the prerelease (e.g. `1.5.0-beta.1` and `beta`), so it never replaces a stable
+/// rolling tag. Build metadata is dropped since `+` is not valid in a Docker tag.
+/// # Arguments
+/// * `tag` - The selected tag
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The Docker tags, or `None` if the tag is not a valid semantic version
+pub fn get_docker_tags(tag: &str, filter: &TagFilter) -> Option<Vec<String>> {
+    let mut version = parse_tag_version(tag, filter)?;
+    version.build = semver::BuildMetadata::EMPTY;
+
+    if !version.pre.is_empty() {
+        let channel = get_channel(&version);
+        return Some(vec![version.to_string(), channel]);
+    }
+
+    Some(vec![
+        version.major.to_string(),
+        format!("{}.{}", version.major, version.minor),
+        version.to_string(),
+        "latest".to_string(),
+    ])
+}
+
+/// Describe the release that follows the latest tag for release note generators
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `latest_tag` - The latest tag, which the release follows
+/// * `filter` - The filter describing how versions are written in the tags
+/// * `bump` - The component to increment, resolved from `Bump::Auto` already
+/// * `finalize` - Release a prerelease as its stable version instead of bumping it
+/// * `limit` - List at most this many commits, or `None` to list them all
+/// # Returns
+/// A JSON object with the `schema_version` of the JSON outputs, the next `tag`, the
+/// `previous_tag` it follows, the `bump` and the `commits` since the previous tag, newest first,
+/// each with its `sha`, `short_sha`, `subject` and `author`
+/// # Errors
+/// Returns an error if the latest tag is not a semantic version or the history cannot be walked
+pub fn get_release_metadata(
+    repository: &Repository,
+    ref_namespace: &str,
+    latest_tag: &str,
+    filter: &TagFilter,
+    bump: Bump,
+    finalize: bool,
+    limit: Option<usize>,
+) -> Result<Value, Box<dyn Error>> {
+    let tag = get_next_tag(latest_tag, filter, bump, finalize)
+        .ok_or_else(|| format!("Failed to parse {latest_tag} as a semantic version"))?;
+    let commits = get_commits_since(repository, ref_namespace, latest_tag, limit, false)
+        .ok_or_else(|| format!("Failed to walk the history since {latest_tag}"))?
+        .iter()
+        .map(|commit| {
+            let message = String::from_utf8_lossy(commit.message_bytes()).into_owned();
+            let author = commit.author();
+            Ok(json!({
+                "sha": commit.id().to_string(),
+                "short_sha": commit.as_object().short_id()?.as_str().unwrap_or_default(),
+                "subject": message.lines().next().unwrap_or_default().trim(),
+                "author": String::from_utf8_lossy(author.name_bytes()),
+            }))
+        })
+        .collect::<Result<Vec<_>, git2::Error>>()?;
+    let bump = bump
+        .to_possible_value()
+        .map(|value| value.get_name().to_string());
+    Ok(json!({
+        "schema_version": SCHEMA_VERSION,
+        "tag": tag,
+        "previous_tag": latest_tag,
+        "bump": bump,
+        "commits": commits,
+    }))
+}
+
+/// Find the tags before the latest stable and prerelease tags, e.g. for a changelog
+/// # Arguments
+/// * `repository` - The repository whose HEAD is checked
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet, whose prerelease mode is overridden for each
+/// * `exclude_head_tag` - Leave the tags pointing at HEAD out, so a tagged HEAD is compared
+///   with the release before it
+/// # Returns
+/// The previous stable and prerelease tags, each empty if there is none. There is no previous
+/// prerelease tag without a prerelease suffix
+/// # Errors
+/// Returns an error if a tag pattern is invalid
+pub fn get_previous_tags(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    filter: &TagFilter,
+    exclude_head_tag: bool,
+) -> Result<(String, String), Box<dyn Error>> {
+    let find_previous = |prerelease: bool| {
+        let filter = TagFilter {
+            prerelease,
+            ..filter.clone()
+        };
+        if exclude_head_tag {
+            find_previous_tag_before_head(repository, ref_namespace, tags, &filter)
+        } else {
+            find_previous_tag(tags, &filter)
+        }
+    };
+    let previous_stable = find_previous(false)?.unwrap_or_default();
+    let previous_prerelease = if filter.prerelease_suffix.is_empty() {
+        String::new()
+    } else {
+        find_previous(true)?.unwrap_or_default()
+    };
+    Ok((previous_stable, previous_prerelease))
+}
+
+/// Find the tag preceding the latest tag, leaving out the tags on HEAD
+///
+/// A tagged HEAD is the release being described, so neither it nor any other tag on the same
+/// commit can be the release before it.
+/// # Arguments
+/// * `repository` - The repository whose HEAD is checked
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The highest matching tag that is below the latest tag and not on HEAD, or `None` if there is
+/// none
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+fn find_previous_tag_before_head(
+    repository: &Repository,
+    ref_namespace: &str,
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(latest_tag) = find_latest_tag(tags, filter)? else {
+        return Ok(None);
+    };
+    let candidates = tags
+        .iter()
+        .copied()
+        .filter(|tag| !is_head_on_tag(repository, ref_namespace, tag))
+        .collect::<Vec<_>>();
+    Ok(get_matching_tags(&candidates, filter)?
+        .into_iter()
+        .find(|tag| compare_tags(tag, &latest_tag, filter).is_lt())
+        .map(|tag| tag.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(tag_prefix: &str, prerelease_suffix: &str, prerelease: bool) -> TagFilter {
+        TagFilter {
+            tag_prefixes: vec![tag_prefix.to_string()],
+            prerelease_suffix: prerelease_suffix.to_string(),
+            prerelease,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_docker_tags() {
+        let docker_filter = filter("v", "beta", false);
+
+        // Check that a stable release gets the full set of rolling tags
+        let docker_tags = get_docker_tags("v1.4.2", &docker_filter).unwrap();
+        assert_eq!(docker_tags, vec!["1", "1.4", "1.4.2", "latest"]);
+
+        // Check that build metadata is dropped
+        let docker_tags = get_docker_tags("v1.4.2+linux", &docker_filter).unwrap();
+        assert_eq!(docker_tags, vec!["1", "1.4", "1.4.2", "latest"]);
+
+        // Check that a prerelease skips the rolling tags
+        let docker_tags = get_docker_tags("v1.5.0-beta.1", &docker_filter).unwrap();
+        assert_eq!(docker_tags, vec!["1.5.0-beta.1", "beta"]);
+
+        // Check that the moving tag follows the channel of the tag
+        let docker_tags = get_docker_tags("v1.5.0-rc.2", &docker_filter).unwrap();
+        assert_eq!(docker_tags, vec!["1.5.0-rc.2", "rc"]);
+
+        // Check that an unparseable tag yields nothing
+        assert_eq!(get_docker_tags("v1.5", &docker_filter), None);
+    }
+}
@@ -0,0 +1,1169 @@
+//! Selecting tags: the filters a tag must pass and the order of the versions that do
+
+use crate::warnings;
+use anstream::println;
+use clap::ValueEnum;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A requirement a version must meet to be selected
+#[derive(Clone, Debug)]
+pub enum Constraint {
+    /// The version must match, with the usual semver rules for prereleases
+    Require(VersionReq),
+    /// The version must not be this one (e.g. a known-bad release)
+    Exclude(Version),
+    /// The version must meet every constraint of at least one of these alternatives
+    AnyOf(Vec<Vec<Constraint>>),
+}
+
+impl Constraint {
+    fn matches(&self, version: &Version) -> bool {
+        match self {
+            Constraint::Require(requirement) => requirement.matches(version),
+            Constraint::Exclude(excluded) => version.cmp_precedence(excluded).is_ne(),
+            Constraint::AnyOf(alternatives) => alternatives.iter().any(|constraints| {
+                constraints
+                    .iter()
+                    .all(|constraint| constraint.matches(version))
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constraint::Require(requirement) => write!(f, "{requirement}"),
+            Constraint::Exclude(excluded) => write!(f, "!={excluded}"),
+            Constraint::AnyOf(alternatives) => {
+                let alternatives = alternatives
+                    .iter()
+                    .map(|constraints| {
+                        constraints
+                            .iter()
+                            .map(Constraint::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, "{}", alternatives.join(" || "))
+            }
+        }
+    }
+}
+
+/// Which end of the version order to select
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Selection {
+    /// The highest matching version
+    Max,
+    /// The lowest matching version (e.g. the minimum supported release)
+    Min,
+}
+
+/// How to order distinct tags with exactly the same version (e.g. `v1.2.3` and `v01.2.3`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TieBreak {
+    /// Prefer the tag on the most recent commit
+    #[default]
+    Date,
+    /// Prefer the tag with the greatest name
+    Name,
+    /// Prefer the tag encountered first
+    First,
+}
+
+/// Criteria used to decide which tags are candidates for selection
+#[derive(Clone, Debug)]
+pub struct TagFilter {
+    /// The prefixes for the tags (e.g., "v"); new tags use the first
+    pub tag_prefixes: Vec<String>,
+    /// The suffix for prerelease tags (e.g. beta, rc)
+    pub prerelease_suffix: String,
+    /// Whether prerelease tags should be included
+    pub prerelease: bool,
+    /// Tags with a version above this ceiling are skipped
+    pub max_version: Option<Version>,
+    /// Only tags with this major version are considered
+    pub major: Option<u64>,
+    /// How to order tags with exactly the same version
+    pub tie_break: TieBreak,
+    /// The commit time of each tag, used by the date tie-break
+    pub commit_times: HashMap<String, i64>,
+    /// The separator between the major, minor and patch components (e.g. "." or "_")
+    pub component_separator: String,
+    /// Whether prerelease tags with any identifier match, rather than only the suffix
+    pub any_prerelease: bool,
+    /// Channels preferred over semver ordering among prereleases of the same core version
+    pub prefer_channels: Vec<String>,
+    /// Tags with one of these versions are skipped
+    pub yanked: Vec<Version>,
+    /// Only tags whose build metadata contains this text are considered
+    pub metadata_contains: Option<String>,
+    /// Whether tags with build metadata are excluded when selecting stable tags
+    pub stable_requires_no_metadata: bool,
+    /// Requirements every selected version must meet
+    pub constraints: Vec<Constraint>,
+}
+
+impl Default for TagFilter {
+    fn default() -> Self {
+        Self {
+            tag_prefixes: vec![String::new()],
+            prerelease_suffix: String::new(),
+            prerelease: false,
+            max_version: None,
+            major: None,
+            tie_break: TieBreak::default(),
+            commit_times: HashMap::new(),
+            component_separator: ".".to_string(),
+            any_prerelease: false,
+            prefer_channels: Vec::new(),
+            yanked: Vec::new(),
+            metadata_contains: None,
+            stable_requires_no_metadata: false,
+            constraints: Vec::new(),
+        }
+    }
+}
+
+/// Optional semver build metadata suffix accepted after the version
+/// Matches the identifiers of any semver prerelease (e.g. `rc.1` or `alpha`)
+const PRERELEASE_PATTERN: &str = r"[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*";
+
+const BUILD_METADATA_PATTERN: &str = r"(\+[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?";
+
+/// Generate the appropriate tag pattern based on whether prerelease tags are considered
+/// # Arguments
+/// * `filter` - The criteria a tag must meet, providing the prefix, prerelease mode and suffix,
+///   and the version component separator
+/// # Returns
+/// A Regex pattern to match the tags
+/// # Errors
+/// Returns an error if the prerelease suffix is empty in prerelease mode or the regex pattern is invalid
+pub fn get_tag_pattern(filter: &TagFilter) -> Result<Regex, Box<dyn Error>> {
+    // Prefixes are literal text, so characters such as `.` in `pkg.core/v` only match themselves
+    let tag_prefix = match filter.tag_prefixes.as_slice() {
+        [tag_prefix] => regex::escape(tag_prefix),
+        tag_prefixes => format!(
+            "(?:{})",
+            tag_prefixes
+                .iter()
+                .map(|tag_prefix| regex::escape(tag_prefix))
+                .collect::<Vec<_>>()
+                .join("|")
+        ),
+    };
+    let prerelease_suffix = regex::escape(&filter.prerelease_suffix);
+    let separator = regex::escape(&filter.component_separator);
+
+    // An empty suffix would match nonsense such as v1.0.0-.1
+    if filter.prerelease && !filter.any_prerelease && prerelease_suffix.is_empty() {
+        return Err("--prerelease-suffix must not be empty when selecting prerelease tags".into());
+    }
+
+    // Build metadata (e.g. +linux) is allowed on both stable and prerelease tags
+    let tag_pattern = if filter.prerelease && filter.any_prerelease {
+        Regex::new(&format!(
+            r"^{tag_prefix}\d+{separator}\d+{separator}\d+-{PRERELEASE_PATTERN}{BUILD_METADATA_PATTERN}$"
+        ))?
+    } else if filter.prerelease {
+        Regex::new(&format!(
+            r"^{}\d+{separator}\d+{separator}\d+-{}\.\d+{BUILD_METADATA_PATTERN}$",
+            tag_prefix, prerelease_suffix
+        ))?
+    } else {
+        Regex::new(&format!(
+            r"^{}\d+{separator}\d+{separator}\d+{BUILD_METADATA_PATTERN}$",
+            tag_prefix
+        ))?
+    };
+
+    Ok(tag_pattern)
+}
+
+/// Get the latest tag from a list of tags based on semantic versioning
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The latest tag as a string
+/// # Errors
+/// Returns an error if no matching tags are found
+pub fn get_latest_tag(tags: &[&str], filter: &TagFilter) -> Result<String, Box<dyn Error>> {
+    find_latest_tag(tags, filter)?.ok_or_else(|| no_matching_tags_error(filter))
+}
+
+/// Build the error reported when no tags match
+/// # Arguments
+/// * `filter` - The criteria no tag met
+/// # Returns
+/// An error naming the tag pattern, or the error building the pattern
+pub fn no_matching_tags_error(filter: &TagFilter) -> Box<dyn Error> {
+    match get_tag_pattern(filter) {
+        Ok(tag_pattern) => format!("No tags found matching pattern: {}", tag_pattern).into(),
+        Err(e) => e,
+    }
+}
+
+/// Find the latest tag from a list of tags based on semantic versioning
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The latest tag, or `None` if no tags match
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn find_latest_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let tags = get_matching_tags(tags, filter)?;
+    Ok(tags.first().map(|tag| tag.to_string()))
+}
+
+/// Find the latest tag across stable and prerelease tags
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria tags must meet; whether prerelease tags are selected is ignored
+/// # Returns
+/// The greater of the latest stable and latest prerelease tag, or `None` if neither exists
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn find_latest_including_prerelease(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let mut candidates = vec![find_latest_tag(
+        tags,
+        &TagFilter {
+            prerelease: false,
+            ..filter.clone()
+        },
+    )?];
+    if !filter.prerelease_suffix.is_empty() {
+        candidates.push(find_latest_tag(
+            tags,
+            &TagFilter {
+                prerelease: true,
+                ..filter.clone()
+            },
+        )?);
+    }
+
+    Ok(candidates
+        .into_iter()
+        .flatten()
+        .max_by(|a, b| compare_tags(a, b, filter).then_with(|| break_tie(a, b, filter))))
+}
+
+/// Find the highest or lowest tag from a list of tags based on semantic versioning
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// * `selection` - Whether to find the highest or the lowest tag
+/// # Returns
+/// The selected tag, or `None` if no tags match
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn find_selected_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+    selection: Selection,
+) -> Result<Option<String>, Box<dyn Error>> {
+    match selection {
+        Selection::Max => find_latest_tag(tags, filter),
+        Selection::Min => find_lowest_tag(tags, filter),
+    }
+}
+
+/// Get the highest or lowest tag from a list of tags based on semantic versioning
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// * `selection` - Whether to get the highest or the lowest tag
+/// # Returns
+/// The selected tag
+/// # Errors
+/// Returns an error if no tags match or the tag pattern is invalid
+pub fn get_selected_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+    selection: Selection,
+) -> Result<String, Box<dyn Error>> {
+    match selection {
+        Selection::Max => get_latest_tag(tags, filter),
+        Selection::Min => {
+            find_lowest_tag(tags, filter)?.ok_or_else(|| no_matching_tags_error(filter))
+        }
+    }
+}
+
+/// Find the lowest tag from a list of tags based on semantic versioning
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The lowest tag, preferring the tie-break winner among tags with the same version, or `None`
+/// if no tags match
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn find_lowest_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let tags = get_matching_tags(tags, filter)?;
+    let Some(lowest) = tags.last() else {
+        return Ok(None);
+    };
+    Ok(tags
+        .iter()
+        .find(|tag| compare_tags(tag, lowest, filter) == Ordering::Equal)
+        .map(|tag| tag.to_string()))
+}
+
+/// Find the tag preceding the latest tag, i.e. the second highest matching tag
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The previous tag, or `None` if fewer than two tags match
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn find_previous_tag(
+    tags: &[&str],
+    filter: &TagFilter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let tags = get_matching_tags(tags, filter)?;
+    Ok(tags.get(1).map(|tag| tag.to_string()))
+}
+
+/// Get all tags matching the filter, sorted from newest to oldest
+/// # Arguments
+/// * `tags` - A slice of tag strings
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// The matching tags in descending order according to `compare_tags`
+/// # Errors
+/// Returns an error if the tag pattern is invalid
+pub fn get_matching_tags<'a>(
+    tags: &[&'a str],
+    filter: &TagFilter,
+) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    let tag_pattern = get_tag_pattern(filter)?;
+
+    let mut tags: Vec<&str> = tags
+        .iter()
+        .copied()
+        .filter(|tag| tag_pattern.is_match(tag))
+        .filter(|tag| passes_version_filters(tag, filter))
+        .collect();
+
+    // The sort is stable, so tags that are still tied keep their original order
+    tags.sort_by(|a, b| compare_tags(b, a, filter).then_with(|| break_tie(b, a, filter)));
+
+    Ok(tags)
+}
+
+/// Compare two versions by semver precedence, ignoring build metadata
+/// # Arguments
+/// * `a` - The first version, with or without a tag prefix (e.g. `v1.2.0` or `1.2.0`)
+/// * `b` - The second version
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The ordering of `a` relative to `b`
+/// # Errors
+/// Returns an error if either is not a semantic version
+pub fn compare_versions(a: &str, b: &str, filter: &TagFilter) -> Result<Ordering, Box<dyn Error>> {
+    let parse = |version: &str| {
+        parse_tag_version(version, filter)
+            .or_else(|| Version::parse(version).ok())
+            .ok_or_else(|| format!("{version} is not a semantic version"))
+    };
+    Ok(parse(a)?.cmp_precedence(&parse(b)?))
+}
+
+/// Compare two tags by their semantic version
+///
+/// Build metadata has no bearing on semver precedence, so `v1.0.0+a` and `v1.0.0+b` are
+/// the same release. To keep selection deterministic, tags with equal precedence are then
+/// ordered alphabetically by their build metadata (no metadata sorts first). Preferred channels
+/// are ranked above semver ordering among prereleases of the same core version. Every code path
+/// that orders or compares tags should go through this function so they agree.
+/// # Arguments
+/// * `a` - The first tag
+/// * `b` - The second tag
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The ordering of `a` relative to `b`, treating unparseable tags as version 0.0.0
+pub fn compare_tags(a: &str, b: &str, filter: &TagFilter) -> Ordering {
+    let a_version = parse_tag_version(a, filter).unwrap_or(Version::new(0, 0, 0));
+    let b_version = parse_tag_version(b, filter).unwrap_or(Version::new(0, 0, 0));
+
+    compare_preferred_channels(&a_version, &b_version, filter)
+        .then_with(|| a_version.cmp_precedence(&b_version))
+        .then_with(|| a_version.build.as_str().cmp(b_version.build.as_str()))
+}
+
+/// Compare two prereleases of the same core version by the preferred channels
+/// # Arguments
+/// * `a` - The first version
+/// * `b` - The second version
+/// * `filter` - The filter listing the preferred channels, most preferred first
+/// # Returns
+/// `Greater` if `a` is in a more preferred channel than `b`, `Less` if less preferred, and
+/// `Equal` if they are not both prereleases of the same core version or are equally preferred
+fn compare_preferred_channels(a: &Version, b: &Version, filter: &TagFilter) -> Ordering {
+    if (a.major, a.minor, a.patch) != (b.major, b.minor, b.patch)
+        || a.pre.is_empty()
+        || b.pre.is_empty()
+    {
+        return Ordering::Equal;
+    }
+
+    // Channels that are not preferred rank below every preferred channel
+    let preference = |version: &Version| {
+        let channel = get_channel(version);
+        filter
+            .prefer_channels
+            .iter()
+            .position(|preferred| *preferred == channel)
+            .map_or(0, |position| filter.prefer_channels.len() - position)
+    };
+    preference(a).cmp(&preference(b))
+}
+
+/// Order two tags that `compare_tags` considers equal using the filter's tie-break strategy
+/// # Arguments
+/// * `a` - The first tag
+/// * `b` - The second tag
+/// * `filter` - The filter holding the tie-break strategy and tag commit times
+/// # Returns
+/// The ordering of `a` relative to `b`, or `Equal` if the strategy cannot tell them apart
+pub fn break_tie(a: &str, b: &str, filter: &TagFilter) -> Ordering {
+    match filter.tie_break {
+        // Tags without a known commit time are treated as the oldest
+        TieBreak::Date => filter.commit_times.get(a).cmp(&filter.commit_times.get(b)),
+        TieBreak::Name => a.cmp(b),
+        TieBreak::First => Ordering::Equal,
+    }
+}
+
+/// Split a tag into its prefix and the version after it
+/// # Arguments
+/// * `tag` - The tag to split
+/// * `filter` - The filter listing the prefixes the tags use
+/// # Returns
+/// The longest prefix the tag starts with and the rest of the tag, or `None` if it starts with
+/// none of them
+pub fn split_tag_prefix<'a>(tag: &'a str, filter: &TagFilter) -> Option<(&'a str, &'a str)> {
+    let prefix = filter
+        .tag_prefixes
+        .iter()
+        .filter(|prefix| tag.starts_with(prefix.as_str()))
+        .max_by_key(|prefix| prefix.len())?;
+    Some(tag.split_at(prefix.len()))
+}
+
+/// Parse the semantic version of a tag after removing its prefix
+/// # Arguments
+/// * `tag` - The tag to parse
+/// * `filter` - The filter describing how versions are written in the tags
+/// # Returns
+/// The parsed version, or `None` if the tag is not a valid semantic version
+pub fn parse_tag_version(tag: &str, filter: &TagFilter) -> Option<Version> {
+    let (_, version) = split_tag_prefix(tag, filter)?;
+    let version = normalize_separator(version, &filter.component_separator);
+    Version::parse(&version)
+        .or_else(|_| Version::parse(&strip_leading_zeros(&version)))
+        .ok()
+}
+
+/// Replace a custom separator between the major, minor and patch components with `.`
+/// # Arguments
+/// * `version` - The version string, without a tag prefix (e.g. `1_2_3-rc.1`)
+/// * `separator` - The separator used between the components (e.g. `_`)
+/// # Returns
+/// The version string with a standard `X.Y.Z` core (e.g. `1.2.3-rc.1`)
+fn normalize_separator(version: &str, separator: &str) -> String {
+    if separator == "." || separator.is_empty() {
+        return version.to_string();
+    }
+
+    // Only the first two separators belong to the core, the rest (e.g. a `-` separator
+    // reappearing before the prerelease) is left alone
+    version.splitn(3, separator).collect::<Vec<_>>().join(".")
+}
+
+/// Remove leading zeros from the numeric components of a version string, so that tags such
+/// as `v01.2.3` (which strict semver rejects) can still be parsed as `1.2.3`
+/// # Arguments
+/// * `version` - The version string, without a tag prefix
+/// # Returns
+/// The version string with leading zeros removed from the core and prerelease identifiers
+fn strip_leading_zeros(version: &str) -> String {
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+
+    let mut normalized = version
+        .split('.')
+        .map(|component| {
+            // Only purely numeric identifiers are affected, e.g. `3-rc` is left alone
+            let rest = component.trim_start_matches(|c: char| c.is_ascii_digit());
+            let digits = &component[..component.len() - rest.len()];
+            let trimmed = digits.trim_start_matches('0');
+            match (digits.is_empty(), trimmed.is_empty()) {
+                (true, _) => component.to_string(),
+                (false, true) => format!("0{rest}"),
+                (false, false) => format!("{trimmed}{rest}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+
+    if let Some(build) = build {
+        normalized.push('+');
+        normalized.push_str(build);
+    }
+
+    normalized
+}
+
+/// Get the release channel of a version from its prerelease identifier
+/// # Arguments
+/// * `version` - The parsed version
+/// # Returns
+/// `stable` for a version without a prerelease, otherwise the prerelease text before the
+/// numeric counter (e.g. `rc` for `1.0.0-rc.1`)
+pub fn get_channel(version: &Version) -> String {
+    if version.pre.is_empty() {
+        return "stable".to_string();
+    }
+
+    get_prerelease_channel(version.pre.as_str())
+}
+
+/// Get the channel name from the prerelease part of a version
+/// # Arguments
+/// * `prerelease` - The prerelease text after the `-` (e.g. `rc.1`)
+/// # Returns
+/// The text before the numeric counter (e.g. `rc`), or `prerelease` if there is none
+pub fn get_prerelease_channel(prerelease: &str) -> String {
+    let channel = prerelease
+        .split('.')
+        .take_while(|identifier| !identifier.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join(".");
+
+    // A bare counter such as 1.0.0-1 has no channel name
+    if channel.is_empty() {
+        "prerelease".to_string()
+    } else {
+        channel
+    }
+}
+
+/// Write a version as a tag
+/// # Arguments
+/// * `version` - The version to write
+/// * `filter` - The prefix and component separator the tags use
+/// # Returns
+/// The tag, without any build metadata (e.g. `v1.2.0-rc.1`)
+pub fn format_tag(version: &Version, filter: &TagFilter) -> String {
+    let separator = &filter.component_separator;
+    let mut tag = format!(
+        "{prefix}{major}{separator}{minor}{separator}{patch}",
+        prefix = filter
+            .tag_prefixes
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default(),
+        major = version.major,
+        minor = version.minor,
+        patch = version.patch,
+    );
+    if !version.pre.is_empty() {
+        tag.push_str(&format!("-{}", version.pre));
+    }
+    tag
+}
+
+/// Check whether a tag's version passes the version based criteria of the filter,
+/// logging any tag that is skipped for a reason other than its major version
+/// # Arguments
+/// * `tag` - The tag to check
+/// * `filter` - The criteria a tag must meet to be considered
+/// # Returns
+/// `true` if the tag should be kept
+fn passes_version_filters(tag: &str, filter: &TagFilter) -> bool {
+    let Some(version) = parse_tag_version(tag, filter) else {
+        return true;
+    };
+
+    if let Some(major) = filter.major
+        && version.major != major
+    {
+        return false;
+    }
+
+    if let Some(metadata) = &filter.metadata_contains
+        && !version.build.contains(metadata.as_str())
+    {
+        skip_tag(tag, &format!("build metadata does not contain {metadata}"));
+        return false;
+    }
+
+    if !filter.prerelease && filter.stable_requires_no_metadata && !version.build.is_empty() {
+        skip_tag(tag, "stable tags must not have build metadata");
+        return false;
+    }
+
+    if let Some(max_version) = &filter.max_version
+        && version.cmp_precedence(max_version).is_gt()
+    {
+        skip_tag(
+            tag,
+            &format!("version is above the maximum version ({max_version})"),
+        );
+        return false;
+    }
+
+    if let Some(constraint) = filter
+        .constraints
+        .iter()
+        .find(|constraint| !constraint.matches(&version))
+    {
+        skip_tag(
+            tag,
+            &format!("version {version} does not satisfy {constraint}"),
+        );
+        return false;
+    }
+
+    if filter
+        .yanked
+        .iter()
+        .any(|yanked| version.cmp_precedence(yanked).is_eq())
+    {
+        skip_tag(tag, &format!("version {version} is yanked"));
+        return false;
+    }
+
+    true
+}
+
+/// Log that a tag is skipped, recording it as a warning
+/// # Arguments
+/// * `tag` - The skipped tag
+/// * `reason` - Why the tag is skipped
+pub fn skip_tag(tag: &str, reason: &str) {
+    let message = format!("Skipping tag {tag}: {reason}");
+    println!("{message}");
+    warnings::record("skipped_tag", Some(tag), &message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(tag_prefix: &str, prerelease_suffix: &str, prerelease: bool) -> TagFilter {
+        TagFilter {
+            tag_prefixes: vec![tag_prefix.to_string()],
+            prerelease_suffix: prerelease_suffix.to_string(),
+            prerelease,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_latest_tag() {
+        // Check that the highest stable tag is returned even with an older prerelease of the same major.minor.patch version
+        let tags = vec!["v1.0.0", "v1.2.0", "v1.1.5", "v2.0.0-beta.0", "v2.0.0"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", false)).unwrap();
+        assert_eq!(latest_tag, "v2.0.0");
+
+        // Check that the highest stable tag is returned even with a newer prerelease of the same major.minor version
+        let tags = vec!["v1.0.0", "v1.2.0", "v1.1.5", "v2.0.0-beta.0"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", false)).unwrap();
+        assert_eq!(latest_tag, "v1.2.0");
+
+        // Check that the highest tag (including prerelease) is returned
+        let tags = vec!["v1.0.0-beta.0", "v1.0.0", "v1.1.0-beta.0"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", true)).unwrap();
+        assert_eq!(latest_tag, "v1.1.0-beta.0");
+
+        // Test with different prerelease suffixes
+        let tags = vec!["v1.0.0-beta.1", "v1.0.0-beta.2"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", true)).unwrap();
+        assert_eq!(latest_tag, "v1.0.0-beta.2");
+
+        // Test with multi-digit prerelease numbers (check that lexical comparison is not used)
+        let tags = vec!["v1.0.0-beta.10", "v1.0.0-beta.2"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", true)).unwrap();
+        assert_eq!(latest_tag, "v1.0.0-beta.10");
+
+        // Check that no matching tags returns an error
+        let tags: Vec<&str> = vec![];
+        let result = get_latest_tag(&tags, &filter("v", "beta", false));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_latest_tag_max_version() {
+        // Check that a placeholder tag above the ceiling is skipped in favour of the runner-up
+        let tags = vec!["v1.0.0", "v1.2.0", "v999.0.0"];
+        let filter = TagFilter {
+            max_version: Some(Version::new(2, 0, 0)),
+            ..filter("v", "beta", false)
+        };
+        let latest_tag = get_latest_tag(&tags, &filter).unwrap();
+        assert_eq!(latest_tag, "v1.2.0");
+
+        // Check that a tag equal to the ceiling is still included
+        let tags = vec!["v1.0.0", "v2.0.0"];
+        let latest_tag = get_latest_tag(&tags, &filter).unwrap();
+        assert_eq!(latest_tag, "v2.0.0");
+
+        // Check that all tags above the ceiling produces the no-match error
+        let tags = vec!["v3.0.0", "v999.0.0"];
+        let result = get_latest_tag(&tags, &filter);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let tag_filter = filter("v", "rc", false);
+        let compare = |a, b| compare_versions(a, b, &tag_filter).unwrap();
+        assert_eq!(compare("v1.2.0", "v1.10.0"), Ordering::Less);
+        assert_eq!(compare("1.2.0-rc.1", "v1.2.0"), Ordering::Less);
+        assert_eq!(compare("v1.2.0+linux", "1.2.0+macos"), Ordering::Equal);
+        assert_eq!(compare("v2.0.0", "v1.99.99"), Ordering::Greater);
+        assert!(compare_versions("v1.2", "v1.2.0", &tag_filter).is_err());
+    }
+
+    #[test]
+    fn test_compare_tags_metadata() {
+        let stable_filter = filter("v", "beta", false);
+
+        // Metadata does not change precedence against other versions
+        assert_eq!(
+            compare_tags("v1.0.0+b", "v1.1.0+a", &stable_filter),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_tags("v1.0.0+zzz", "v1.0.0-beta.1", &stable_filter),
+            Ordering::Greater
+        );
+
+        // Metadata-only differences are ordered alphabetically, with no metadata first
+        assert_eq!(
+            compare_tags("v1.0.0+a", "v1.0.0+b", &stable_filter),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_tags("v1.0.0", "v1.0.0+a", &stable_filter),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_tags("v1.0.0+10", "v1.0.0+9", &stable_filter),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_tags("v1.0.0+a", "v1.0.0+a", &stable_filter),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_metadata_consistent_across_selection() {
+        // Selection picks the same tag regardless of the input order
+        let tags = vec!["v1.0.0+b", "v1.0.0", "v1.0.0+a"];
+        let mut reversed = tags.clone();
+        reversed.reverse();
+        let stable_filter = filter("v", "beta", false);
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "v1.0.0+b");
+        assert_eq!(
+            get_latest_tag(&reversed, &stable_filter).unwrap(),
+            "v1.0.0+b"
+        );
+
+        // The same applies to prerelease selection
+        let tags = vec!["v1.0.0-beta.1+b", "v1.0.0-beta.1+a", "v1.0.0-beta.0+z"];
+        let prerelease_filter = filter("v", "beta", true);
+        assert_eq!(
+            get_latest_tag(&tags, &prerelease_filter).unwrap(),
+            "v1.0.0-beta.1+b"
+        );
+
+        // Metadata never lets an older version outrank a newer one
+        let tags = vec!["v1.0.0+zzz", "v1.0.1"];
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "v1.0.1");
+    }
+
+    #[test]
+    fn test_get_latest_tag_major() {
+        let tags = vec![
+            "v0.9.0",
+            "v1.0.0",
+            "v1.4.2",
+            "v1.5.0-beta.1",
+            "v2.0.0",
+            "v2.1.0-beta.0",
+        ];
+
+        // Check that the latest of the requested major line is returned
+        let major_filter = TagFilter {
+            major: Some(1),
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(get_latest_tag(&tags, &major_filter).unwrap(), "v1.4.2");
+
+        // Check that prerelease selection is also limited to the major line
+        let major_filter = TagFilter {
+            prerelease: true,
+            ..major_filter
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &major_filter).unwrap(),
+            "v1.5.0-beta.1"
+        );
+
+        // Check that a major line without tags produces the no-match error
+        let major_filter = TagFilter {
+            major: Some(3),
+            ..filter("v", "beta", false)
+        };
+        assert!(get_latest_tag(&tags, &major_filter).is_err());
+    }
+
+    #[test]
+    fn test_find_previous_tag() {
+        let tags = vec![
+            "v1.0.0",
+            "v1.2.0",
+            "v1.1.0",
+            "v1.3.0-beta.1",
+            "v1.3.0-beta.3",
+            "v1.3.0-beta.2",
+        ];
+
+        // Check that the second highest tag of each channel is returned
+        let previous_stable = find_previous_tag(&tags, &filter("v", "beta", false)).unwrap();
+        assert_eq!(previous_stable.as_deref(), Some("v1.1.0"));
+        let previous_prerelease = find_previous_tag(&tags, &filter("v", "beta", true)).unwrap();
+        assert_eq!(previous_prerelease.as_deref(), Some("v1.3.0-beta.2"));
+
+        // Check that a channel with a single tag has no previous tag
+        let tags = vec!["v1.0.0", "v1.1.0-beta.1"];
+        assert_eq!(
+            find_previous_tag(&tags, &filter("v", "beta", false)).unwrap(),
+            None
+        );
+        assert_eq!(
+            find_previous_tag(&tags, &filter("v", "beta", true)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_empty_prerelease_suffix() {
+        let tags = vec!["v1.0.0", "v1.0.0-.1", "v1.1.0-beta.1"];
+
+        // Check that prerelease mode rejects an empty suffix with a clear error
+        let error = get_latest_tag(&tags, &filter("v", "", true)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("--prerelease-suffix must not be empty")
+        );
+
+        // Check that stable mode does not need a suffix
+        let latest_tag = get_latest_tag(&tags, &filter("v", "", false)).unwrap();
+        assert_eq!(latest_tag, "v1.0.0");
+    }
+
+    #[test]
+    fn test_get_channel() {
+        let channel = |version: &str| get_channel(&Version::parse(version).unwrap());
+        assert_eq!(channel("1.0.0"), "stable");
+        assert_eq!(channel("1.0.0+linux"), "stable");
+        assert_eq!(channel("1.0.0-rc.1"), "rc");
+        assert_eq!(channel("1.0.0-beta.2"), "beta");
+        assert_eq!(channel("1.0.0-alpha"), "alpha");
+        assert_eq!(channel("1.0.0-1"), "prerelease");
+    }
+
+    #[test]
+    fn test_find_latest_including_prerelease() {
+        let stable_filter = filter("v", "beta", false);
+        let tags = vec!["v1.0.0", "v1.1.0-beta.1", "x2.0.0"];
+
+        // Check that a higher prerelease is reported alongside the latest stable tag
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "v1.0.0");
+        assert_eq!(
+            find_latest_including_prerelease(&tags, &stable_filter).unwrap(),
+            Some("v1.1.0-beta.1".to_string())
+        );
+
+        // Check that a stable release outranks its prereleases
+        let tags = vec!["v1.0.0", "v1.1.0-beta.1", "v1.1.0"];
+        assert_eq!(
+            find_latest_including_prerelease(&tags, &filter("v", "beta", true)).unwrap(),
+            Some("v1.1.0".to_string())
+        );
+
+        assert_eq!(
+            find_latest_including_prerelease(&["x1.0.0"], &stable_filter).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prefer_channel() {
+        let any_filter = TagFilter {
+            any_prerelease: true,
+            ..filter("v", "", true)
+        };
+        let tags = vec![
+            "v1.2.0-beta.2",
+            "v1.2.0-rc.1",
+            "v1.1.0-rc.9",
+            "v1.2.0-test.1",
+            "v1.2.0",
+        ];
+
+        // Check that plain semver ordering picks the lexically greatest identifier
+        assert_eq!(get_latest_tag(&tags, &any_filter).unwrap(), "v1.2.0-test.1");
+
+        // Check that preferred channels override semver ordering among equal cores
+        let rc_filter = TagFilter {
+            prefer_channels: vec!["rc".to_string()],
+            ..any_filter.clone()
+        };
+        assert_eq!(get_latest_tag(&tags, &rc_filter).unwrap(), "v1.2.0-rc.1");
+        let beta_filter = TagFilter {
+            prefer_channels: vec!["beta".to_string(), "rc".to_string()],
+            ..any_filter.clone()
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &beta_filter).unwrap(),
+            "v1.2.0-beta.2"
+        );
+        assert_eq!(
+            find_previous_tag(&tags, &beta_filter).unwrap(),
+            Some("v1.2.0-rc.1".to_string())
+        );
+
+        // Check that a higher core version still wins over a preferred channel
+        let tags = vec!["v1.2.0-rc.1", "v1.3.0-alpha.1"];
+        assert_eq!(get_latest_tag(&tags, &rc_filter).unwrap(), "v1.3.0-alpha.1");
+    }
+
+    #[test]
+    fn test_metadata_contains() {
+        let linux_filter = TagFilter {
+            metadata_contains: Some("linux".to_string()),
+            ..filter("v", "rc", false)
+        };
+        let tags = vec![
+            "v1.2.0+windows",
+            "v1.1.0+linux.x86-64",
+            "v1.1.0+linux.arm64",
+            "v1.0.0+linux",
+            "v1.3.0",
+            "v1.4.0-rc.1+linux",
+        ];
+
+        // Check that only tags for the platform are selected, ordered by core version
+        assert_eq!(
+            get_latest_tag(&tags, &linux_filter).unwrap(),
+            "v1.1.0+linux.x86-64"
+        );
+        assert_eq!(
+            find_lowest_tag(&tags, &linux_filter).unwrap(),
+            Some("v1.0.0+linux".to_string())
+        );
+        let rc_filter = TagFilter {
+            prerelease: true,
+            ..linux_filter.clone()
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &rc_filter).unwrap(),
+            "v1.4.0-rc.1+linux"
+        );
+        let arm_filter = TagFilter {
+            metadata_contains: Some("arm".to_string()),
+            ..linux_filter
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &arm_filter).unwrap(),
+            "v1.1.0+linux.arm64"
+        );
+        let mac_filter = TagFilter {
+            metadata_contains: Some("macos".to_string()),
+            ..filter("v", "rc", false)
+        };
+        assert!(get_latest_tag(&tags, &mac_filter).is_err());
+    }
+
+    #[test]
+    fn test_stable_requires_no_metadata() {
+        let tags = vec!["v1.0.0+rc", "v0.9.0", "v1.1.0-rc.1+linux"];
+        let stable_filter = filter("v", "rc", false);
+        assert_eq!(get_latest_tag(&tags, &stable_filter).unwrap(), "v1.0.0+rc");
+
+        // Check that build metadata excludes only stable tags under the flag
+        let strict_filter = TagFilter {
+            stable_requires_no_metadata: true,
+            ..stable_filter
+        };
+        assert_eq!(get_latest_tag(&tags, &strict_filter).unwrap(), "v0.9.0");
+        let rc_filter = TagFilter {
+            prerelease: true,
+            ..strict_filter
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &rc_filter).unwrap(),
+            "v1.1.0-rc.1+linux"
+        );
+    }
+
+    #[test]
+    fn test_find_lowest_tag() {
+        let tags = vec![
+            "v1.2.0",
+            "v0.9.1",
+            "v1.0.0-beta.3",
+            "v0.10.0-beta.2",
+            "v0.10.0-beta.1",
+            "v0.9.0+linux",
+            "v0.9.0",
+            "x0.1.0",
+        ];
+
+        // Check that the lowest stable and lowest prerelease tags are selected in each mode
+        assert_eq!(
+            find_lowest_tag(&tags, &filter("v", "beta", false)).unwrap(),
+            Some("v0.9.0".to_string())
+        );
+        assert_eq!(
+            find_lowest_tag(&tags, &filter("v", "beta", true)).unwrap(),
+            Some("v0.10.0-beta.1".to_string())
+        );
+
+        // Check that the same filtering applies as for the latest tag
+        let major_filter = TagFilter {
+            major: Some(1),
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(
+            find_lowest_tag(&tags, &major_filter).unwrap(),
+            Some("v1.2.0".to_string())
+        );
+        assert_eq!(find_lowest_tag(&[], &major_filter).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_tag_version_leading_zeros() {
+        let stable_filter = filter("v", "beta", false);
+
+        // Check that leading zeros are tolerated and canonicalised
+        let version = parse_tag_version("v01.2.3", &stable_filter).unwrap();
+        assert_eq!(version.to_string(), "1.2.3");
+        let version = parse_tag_version("v1.00.03-rc.01+build.007", &stable_filter).unwrap();
+        assert_eq!(version.to_string(), "1.0.3-rc.1+build.007");
+        let version = parse_tag_version("v0.0.0", &stable_filter).unwrap();
+        assert_eq!(version.to_string(), "0.0.0");
+
+        // Check that the original tag is still the one selected and ordering uses the parsed value
+        let tags = vec!["v01.2.3", "v1.2.2"];
+        let latest_tag = get_latest_tag(&tags, &stable_filter).unwrap();
+        assert_eq!(latest_tag, "v01.2.3");
+
+        // Check that tags which are not versions at all still fail to parse
+        assert_eq!(parse_tag_version("v1.2", &stable_filter), None);
+        assert_eq!(parse_tag_version("release-1.2.3", &stable_filter), None);
+    }
+
+    #[test]
+    fn test_tie_break() {
+        // Both tags parse to exactly the same version
+        let tags = vec!["v01.2.3", "v1.2.3", "v1.2.2"];
+        let reversed = vec!["v1.2.2", "v1.2.3", "v01.2.3"];
+
+        // Check that the date tie-break prefers the most recent commit
+        let date_filter = TagFilter {
+            tie_break: TieBreak::Date,
+            commit_times: HashMap::from([
+                ("v01.2.3".to_string(), 2000),
+                ("v1.2.3".to_string(), 1000),
+                ("v1.2.2".to_string(), 3000),
+            ]),
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(get_latest_tag(&tags, &date_filter).unwrap(), "v01.2.3");
+        assert_eq!(get_latest_tag(&reversed, &date_filter).unwrap(), "v01.2.3");
+
+        // Check that the name tie-break prefers the greatest name regardless of order
+        let name_filter = TagFilter {
+            tie_break: TieBreak::Name,
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(get_latest_tag(&tags, &name_filter).unwrap(), "v1.2.3");
+        assert_eq!(get_latest_tag(&reversed, &name_filter).unwrap(), "v1.2.3");
+
+        // Check that the first tie-break keeps whichever tag was encountered first
+        let first_filter = TagFilter {
+            tie_break: TieBreak::First,
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(get_latest_tag(&tags, &first_filter).unwrap(), "v01.2.3");
+        assert_eq!(get_latest_tag(&reversed, &first_filter).unwrap(), "v1.2.3");
+    }
+
+    #[test]
+    fn test_component_separator() {
+        // Check underscore separated versions are matched and ordered numerically
+        let tags = vec!["v1_2_3", "v1_10_0", "v1.11.0", "v1_9_9"];
+        let underscore_filter = TagFilter {
+            component_separator: "_".to_string(),
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &underscore_filter).unwrap(),
+            "v1_10_0"
+        );
+        let version = parse_tag_version("v1_10_0", &underscore_filter).unwrap();
+        assert_eq!(version, Version::new(1, 10, 0));
+
+        // Check dash separated versions, including a dash before the prerelease
+        let tags = vec!["v1-2-3", "v1-3-0-beta.1", "v1-3-0-beta.2", "v1.4.0"];
+        let dash_filter = TagFilter {
+            component_separator: "-".to_string(),
+            ..filter("v", "beta", false)
+        };
+        assert_eq!(get_latest_tag(&tags, &dash_filter).unwrap(), "v1-2-3");
+        let dash_filter = TagFilter {
+            prerelease: true,
+            ..dash_filter
+        };
+        assert_eq!(
+            get_latest_tag(&tags, &dash_filter).unwrap(),
+            "v1-3-0-beta.2"
+        );
+        let version = parse_tag_version("v1-3-0-beta.2", &dash_filter).unwrap();
+        assert_eq!(version.to_string(), "1.3.0-beta.2");
+
+        // Check that the default separator no longer matches arbitrary characters
+        let tags = vec!["v1x2x3", "v1.2.0"];
+        let latest_tag = get_latest_tag(&tags, &filter("v", "beta", false)).unwrap();
+        assert_eq!(latest_tag, "v1.2.0");
+    }
+}
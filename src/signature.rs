@@ -0,0 +1,246 @@
+//! Checking the GPG and SSH signatures of annotated tags
+
+use git2::Repository;
+#[cfg(feature = "verify-key")]
+use std::error::Error;
+#[cfg(feature = "verify-key")]
+use std::fs::{read_to_string, write};
+#[cfg(feature = "verify-key")]
+use std::path::Path;
+#[cfg(feature = "verify-key")]
+use std::process::Command;
+#[cfg(feature = "verify-key")]
+use tempfile::TempDir;
+
+/// The armor headers git appends to the message of a signed tag
+const SIGNATURE_HEADERS: [&str; 3] = [
+    "-----BEGIN PGP SIGNATURE-----",
+    "-----BEGIN SSH SIGNATURE-----",
+    "-----BEGIN SIGNED MESSAGE-----",
+];
+
+/// The principal the keys are listed under when verifying SSH signatures
+#[cfg(feature = "verify-key")]
+const SSH_PRINCIPAL: &str = "verify-key";
+
+/// Check whether a tag is an annotated tag carrying a signature
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// # Returns
+/// `true` if the tag is annotated and signed, `false` for lightweight, unsigned or missing tags
+pub fn is_signed_tag(repository: &Repository, ref_namespace: &str, tag: &str) -> bool {
+    let Ok(reference) = repository.find_reference(&format!("{ref_namespace}/{tag}")) else {
+        return false;
+    };
+    let Ok(tag) = reference.peel_to_tag() else {
+        return false;
+    };
+    let message = String::from_utf8_lossy(tag.message_bytes().unwrap_or_default());
+    SIGNATURE_HEADERS
+        .iter()
+        .any(|header| message.contains(header))
+}
+
+/// Verify the signature of a tag against trusted public keys using `git verify-tag`
+///
+/// A key file starting with an SSH key type (e.g. `ssh-ed25519`) is read as one SSH public key
+/// per line, anything else is imported into a throwaway GPG keyring.
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `key_path` - The file holding the trusted public keys
+/// # Errors
+/// Returns an error if the keys cannot be read, `git` cannot be run, or the tag is not signed by
+/// one of the keys
+#[cfg(feature = "verify-key")]
+pub fn verify_tag(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    key_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let keyring = TempDir::new()?;
+    let output = verify_tag_command(repository, Some(key_path), &keyring)?
+        .arg("verify-tag")
+        .arg(format!("{ref_namespace}/{tag}"))
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "The signature of tag {tag} could not be verified against {}: {}",
+            key_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Find who signed a tag using `git verify-tag`
+///
+/// The signer is found even if the signature cannot be verified, so this does not replace
+/// `verify_tag`.
+/// # Arguments
+/// * `repository` - The repository containing the tag
+/// * `ref_namespace` - The ref namespace the tags are in (e.g. `refs/tags`)
+/// * `tag` - The name of the tag
+/// * `key_path` - The file holding the trusted public keys, or `None` to use the GPG keyring of
+///   the user
+/// # Returns
+/// The email address of a GPG signer whose key is known, otherwise the id of the GPG key or the
+/// fingerprint of the SSH key, or `None` if the tag is not signed
+/// # Errors
+/// Returns an error if the keys cannot be read or `git` cannot be run
+#[cfg(feature = "signer-output")]
+pub fn get_signer(
+    repository: &Repository,
+    ref_namespace: &str,
+    tag: &str,
+    key_path: Option<&Path>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    if !is_signed_tag(repository, ref_namespace, tag) {
+        return Ok(None);
+    }
+
+    let keyring = TempDir::new()?;
+    let output = verify_tag_command(repository, key_path, &keyring)?
+        .arg("verify-tag")
+        .arg("--raw")
+        .arg(format!("{ref_namespace}/{tag}"))
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    Ok(parse_signer(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Build a `git` command that checks signatures against the keys in a file, read as described
+/// for `verify_tag`
+///
+/// Without a key file, SSH signatures are checked against no keys, which still names the key
+/// that made them.
+/// # Arguments
+/// * `repository` - The repository containing the tags
+/// * `key_path` - The file holding the trusted public keys, if any
+/// * `keyring` - The directory to keep the keys in while the command runs
+/// # Errors
+/// Returns an error if the keys cannot be read or imported
+#[cfg(feature = "verify-key")]
+fn verify_tag_command(
+    repository: &Repository,
+    key_path: Option<&Path>,
+    keyring: &TempDir,
+) -> Result<Command, Box<dyn Error>> {
+    let keys = match key_path {
+        Some(key_path) => read_to_string(key_path)
+            .map_err(|e| format!("Failed to read the keys in {}: {e}", key_path.display()))?,
+        None => String::new(),
+    };
+
+    let mut command = Command::new("git");
+    command.arg("--git-dir").arg(repository.path());
+    let allowed_signers = keyring.path().join("allowed_signers");
+    command.arg("-c").arg(format!(
+        "gpg.ssh.allowedSignersFile={}",
+        allowed_signers.display()
+    ));
+    match key_path {
+        Some(key_path) if !is_ssh_key(&keys) => {
+            write(&allowed_signers, "")?;
+            let import = Command::new("gpg")
+                .arg("--batch")
+                .arg("--import")
+                .arg(key_path)
+                .env("GNUPGHOME", keyring.path())
+                .output()
+                .map_err(|e| format!("Failed to run gpg: {e}"))?;
+            if !import.status.success() {
+                return Err(format!(
+                    "Failed to import the keys in {}: {}",
+                    key_path.display(),
+                    String::from_utf8_lossy(&import.stderr).trim()
+                )
+                .into());
+            }
+            command.env("GNUPGHOME", keyring.path());
+        }
+        _ => {
+            let entries = keys
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|key| format!("{SSH_PRINCIPAL} namespaces=\"git\" {key}\n"))
+                .collect::<String>();
+            write(&allowed_signers, entries)?;
+        }
+    }
+    Ok(command)
+}
+
+/// Find the signer in the output of `git verify-tag --raw`
+///
+/// GPG reports status lines such as `[GNUPG:] GOODSIG <key id> <name> <<email>>`, or only the
+/// key id when the key is unknown. SSH reports the fingerprint of the key (e.g.
+/// `Good "git" signature with ED25519 key SHA256:...`).
+/// # Arguments
+/// * `output` - The error output of the command
+/// # Returns
+/// The email address of the signer if known, otherwise the key id or fingerprint
+#[cfg(feature = "signer-output")]
+fn parse_signer(output: &str) -> Option<String> {
+    let mut key_id = None;
+    for line in output.lines() {
+        let Some(status) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let mut fields = status.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some("GOODSIG" | "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" | "BADSIG"), Some(id)) => {
+                let user_id = fields.collect::<Vec<_>>().join(" ");
+                if let Some((_, email)) = user_id.rsplit_once('<')
+                    && let Some(email) = email.strip_suffix('>')
+                {
+                    return Some(email.to_string());
+                }
+                key_id = Some(id.to_string());
+            }
+            (Some("ERRSIG" | "NO_PUBKEY"), Some(id)) => {
+                key_id.get_or_insert_with(|| id.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    let fingerprint = regex::Regex::new(r"key (SHA256:\S+)").ok()?;
+    key_id.or_else(|| Some(fingerprint.captures(output)?[1].to_string()))
+}
+
+/// Check whether a key file holds SSH public keys rather than GPG keys
+#[cfg(feature = "verify-key")]
+fn is_ssh_key(keys: &str) -> bool {
+    let key_type = keys.split_whitespace().next().unwrap_or_default();
+    key_type.starts_with("ssh-") || key_type.starts_with("ecdsa-") || key_type.starts_with("sk-")
+}
+
+#[cfg(all(test, feature = "signer-output"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signer() {
+        let known = "[GNUPG:] NEWSIG\n[GNUPG:] KEY_CONSIDERED 0123 0\n[GNUPG:] GOODSIG 89ABCDEF01234567 Alice Smith <alice@example.com>\n";
+        assert_eq!(parse_signer(known).as_deref(), Some("alice@example.com"));
+
+        let unknown = "[GNUPG:] NEWSIG\n[GNUPG:] ERRSIG 89ABCDEF01234567 1 10 00 1700000000 9 -\n[GNUPG:] NO_PUBKEY 89ABCDEF01234567\n";
+        assert_eq!(parse_signer(unknown).as_deref(), Some("89ABCDEF01234567"));
+
+        let ssh = "Good \"git\" signature with ED25519 key SHA256:TMoU1/2mw9IXq0wbsKhu\nNo principal matched.\n";
+        assert_eq!(
+            parse_signer(ssh).as_deref(),
+            Some("SHA256:TMoU1/2mw9IXq0wbsKhu")
+        );
+
+        assert_eq!(parse_signer("error: no signature found\n"), None);
+    }
+}
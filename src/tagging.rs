@@ -0,0 +1,155 @@
+//! Creating the annotated tag for the next release
+
+use git2::{Commit, Oid, Repository, Signature};
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// An annotated tag that is about to be created
+pub struct NewTag<'repo> {
+    /// The name of the tag (e.g. `v1.2.1`)
+    pub name: String,
+    /// The ref the tag is created at (e.g. `refs/tags/v1.2.1`)
+    pub reference: String,
+    /// The commit the tag points to
+    pub target: Commit<'repo>,
+    /// The annotation message
+    pub message: String,
+    /// The person creating the tag
+    pub tagger: Signature<'static>,
+}
+
+/// Work out the tag to create on HEAD
+///
+/// The tagger is read from the `user.name` and `user.email` settings, falling back to
+/// `GIT_COMMITTER_NAME` and `GIT_COMMITTER_EMAIL` like git does.
+/// # Arguments
+/// * `repository` - The repository to tag
+/// * `ref_namespace` - The namespace the tag ref is created in (e.g. `refs/tags`)
+/// * `name` - The name of the tag
+/// # Returns
+/// The tag, with `Release <name>` as its message
+/// # Errors
+/// Returns an error if HEAD is not a commit, the tag already exists or no tagger is configured
+pub fn plan_tag<'repo>(
+    repository: &'repo Repository,
+    ref_namespace: &str,
+    name: &str,
+) -> Result<NewTag<'repo>, Box<dyn Error>> {
+    let reference = format!("{ref_namespace}/{name}");
+    if repository.find_reference(&reference).is_ok() {
+        return Err(format!("Tag {name} already exists").into());
+    }
+    let target = repository.head()?.peel_to_commit()?;
+    let tagger = match repository.signature() {
+        Ok(signature) => signature.to_owned(),
+        Err(_) => match (
+            env::var("GIT_COMMITTER_NAME"),
+            env::var("GIT_COMMITTER_EMAIL"),
+        ) {
+            (Ok(name), Ok(email)) => Signature::now(&name, &email)?,
+            _ => {
+                return Err(
+                    "No tagger configured, set user.name and user.email or GIT_COMMITTER_NAME and GIT_COMMITTER_EMAIL"
+                        .into(),
+                );
+            }
+        },
+    };
+
+    Ok(NewTag {
+        name: name.to_string(),
+        reference,
+        target,
+        message: format!("Release {name}"),
+        tagger,
+    })
+}
+
+/// Describe a tag for review before it is created
+/// # Arguments
+/// * `tag` - The tag to describe
+/// # Returns
+/// The name, target commit, tagger and message of the tag, one per line
+pub fn describe_tag(tag: &NewTag) -> String {
+    let tagger = &tag.tagger;
+    format!(
+        "Tag: {}\nTarget: {} ({})\nTagger: {} <{}>\nMessage: {}",
+        tag.name,
+        tag.target.id(),
+        tag.target.summary().unwrap_or_default(),
+        tagger.name().unwrap_or_default(),
+        tagger.email().unwrap_or_default(),
+        tag.message
+    )
+}
+
+/// Create an annotated tag
+/// # Arguments
+/// * `repository` - The repository to tag
+/// * `tag` - The tag to create
+/// # Returns
+/// The id of the new tag object
+/// # Errors
+/// Returns an error if the tag cannot be written, e.g. because it was created in the meantime
+pub fn create_tag(repository: &Repository, tag: &NewTag) -> Result<Oid, Box<dyn Error>> {
+    let id = repository.tag_annotation_create(
+        &tag.name,
+        tag.target.as_object(),
+        &tag.tagger,
+        &tag.message,
+    )?;
+    repository.reference(&tag.reference, id, false, &format!("tag: {}", tag.name))?;
+    Ok(id)
+}
+
+/// Check whether the tool runs in CI, where nobody can answer a prompt
+/// # Arguments
+/// * `env_var` - Looks up an environment variable
+/// # Returns
+/// `true` if `CI` or `GITHUB_ACTIONS` is set to anything but an empty string or `false`
+pub fn is_ci(env_var: impl Fn(&str) -> Option<String>) -> bool {
+    ["CI", "GITHUB_ACTIONS"]
+        .iter()
+        .filter_map(|name| env_var(name))
+        .any(|value| !value.is_empty() && value != "false")
+}
+
+/// Ask a yes or no question on the terminal
+/// # Arguments
+/// * `question` - The question to print
+/// * `input` - Where the answer is read from
+/// # Returns
+/// `true` only if the answer is `y` or `yes`; no answer at all counts as no
+/// # Errors
+/// Returns an error if the question cannot be printed or the answer cannot be read
+pub fn confirm(question: &str, input: &mut impl BufRead) -> io::Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ci() {
+        assert!(is_ci(|name| (name == "CI").then(|| "true".to_string())));
+        assert!(is_ci(
+            |name| (name == "GITHUB_ACTIONS").then(|| "true".to_string())
+        ));
+        assert!(!is_ci(|name| (name == "CI").then(|| "false".to_string())));
+        assert!(!is_ci(|_| None));
+    }
+
+    #[test]
+    fn test_confirm() {
+        assert!(confirm("Create tag v1.0.0?", &mut "y\n".as_bytes()).unwrap());
+        assert!(confirm("Create tag v1.0.0?", &mut " Yes \n".as_bytes()).unwrap());
+        assert!(!confirm("Create tag v1.0.0?", &mut "n\n".as_bytes()).unwrap());
+        assert!(!confirm("Create tag v1.0.0?", &mut "".as_bytes()).unwrap());
+    }
+}
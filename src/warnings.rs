@@ -0,0 +1,66 @@
+//! Collecting the warnings of a run so they can be written as the `warnings` output
+
+use serde_json::{Value, json};
+use std::cell::RefCell;
+
+thread_local! {
+    /// The warnings recorded so far, in the order they were first raised
+    static WARNINGS: RefCell<Vec<Value>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Record a warning for the `warnings` output
+///
+/// The same tag is checked by several filters in one run, so a warning identical to one
+/// already recorded is dropped.
+/// # Arguments
+/// * `kind` - What the warning is about (e.g. `skipped_tag`)
+/// * `tag` - The tag the warning is about, if any
+/// * `message` - The text printed in the log
+pub fn record(kind: &str, tag: Option<&str>, message: &str) {
+    let warning = json!({"kind": kind, "tag": tag, "message": message});
+    WARNINGS.with_borrow_mut(|warnings| {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    });
+}
+
+/// Take the warnings recorded so far, leaving none behind
+/// # Returns
+/// The warnings as JSON objects with a `kind`, `tag` and `message`
+pub fn take() -> Vec<Value> {
+    WARNINGS.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        record(
+            "skipped_tag",
+            Some("v9.0.0"),
+            "Skipping tag v9.0.0: version 9.0.0 is yanked",
+        );
+        record("detached_head", None, "HEAD is detached");
+        record(
+            "skipped_tag",
+            Some("v9.0.0"),
+            "Skipping tag v9.0.0: version 9.0.0 is yanked",
+        );
+
+        assert_eq!(
+            take(),
+            [
+                json!({
+                    "kind": "skipped_tag",
+                    "tag": "v9.0.0",
+                    "message": "Skipping tag v9.0.0: version 9.0.0 is yanked"
+                }),
+                json!({"kind": "detached_head", "tag": null, "message": "HEAD is detached"}),
+            ]
+        );
+        assert!(take().is_empty());
+    }
+}
@@ -0,0 +1,3146 @@
+use git2::{Oid, Repository, RepositoryInitOptions, Signature, Time};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+/// A throwaway git repository used to exercise the binary end to end
+struct TestRepo {
+    dir: TempDir,
+    repo: Repository,
+}
+
+impl TestRepo {
+    /// Create an empty repository with `main` as the initial branch
+    fn new() -> Self {
+        Self::init(false)
+    }
+
+    /// Create an empty bare repository with `main` as the initial branch
+    fn new_bare() -> Self {
+        Self::init(true)
+    }
+
+    fn init(bare: bool) -> Self {
+        let dir = TempDir::new().unwrap();
+        let mut options = RepositoryInitOptions::new();
+        options.initial_head("main").bare(bare);
+        let repo = Repository::init_opts(dir.path(), &options).unwrap();
+        Self { dir, repo }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Commit an empty tree change on HEAD with the given message
+    fn commit(&self, message: &str) -> Oid {
+        let parent = self.repo.head().ok().and_then(|h| h.target());
+        let parents = parent.into_iter().collect::<Vec<_>>();
+        self.commit_with_parents(Some("HEAD"), message, &parents)
+    }
+
+    /// Commit an empty tree change on HEAD with the given message and commit time
+    fn commit_at(&self, message: &str, seconds: i64) -> Oid {
+        let signature =
+            Signature::new("Test User", "test@example.com", &Time::new(seconds, 0)).unwrap();
+        let parent = self.repo.head().ok().and_then(|h| h.target());
+        let parents = parent.into_iter().collect::<Vec<_>>();
+        self.commit_with_signature(Some("HEAD"), message, &parents, &signature)
+    }
+
+    /// Create a commit with explicit parents, optionally updating a reference to point at it
+    fn commit_with_parents(&self, update_ref: Option<&str>, message: &str, parents: &[Oid]) -> Oid {
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        self.commit_with_signature(update_ref, message, parents, &signature)
+    }
+
+    /// Create a commit like `commit_with_parents`, authored and committed by `signature`
+    fn commit_with_signature(
+        &self,
+        update_ref: Option<&str>,
+        message: &str,
+        parents: &[Oid],
+        signature: &Signature,
+    ) -> Oid {
+        let tree_id = self.repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = self.repo.find_tree(tree_id).unwrap();
+        let parents = parents
+            .iter()
+            .map(|parent| self.repo.find_commit(*parent).unwrap())
+            .collect::<Vec<_>>();
+        let parents = parents.iter().collect::<Vec<_>>();
+        self.repo
+            .commit(update_ref, signature, signature, message, &tree, &parents)
+            .unwrap()
+    }
+
+    /// Create a lightweight tag pointing at the given commit
+    fn tag_commit(&self, name: &str, commit: Oid) {
+        let commit = self.repo.find_commit(commit).unwrap();
+        self.repo
+            .tag_lightweight(name, commit.as_object(), false)
+            .unwrap();
+    }
+
+    /// Create a branch at HEAD and check it out
+    fn checkout_new_branch(&self, name: &str) {
+        let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+        self.repo.branch(name, &head, false).unwrap();
+        self.repo.set_head(&format!("refs/heads/{name}")).unwrap();
+    }
+
+    /// Create an annotated tag pointing at HEAD
+    fn annotated_tag(&self, name: &str, message: &str) {
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+        self.repo
+            .tag(name, head.as_object(), &signature, message, false)
+            .unwrap();
+    }
+
+    /// Create a lightweight tag pointing at HEAD
+    fn tag(&self, name: &str) {
+        let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+        self.repo
+            .tag_lightweight(name, head.as_object(), false)
+            .unwrap();
+    }
+}
+
+/// The result of running the binary
+struct RunResult {
+    success: bool,
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    output_file: String,
+    outputs: HashMap<String, String>,
+}
+
+/// Run the binary in `dir` with the given arguments, capturing the GitHub outputs it writes
+fn run(dir: &Path, args: &[&str]) -> RunResult {
+    run_with_env(dir, args, &[])
+}
+
+/// Run the binary like `run`, with extra environment variables set
+fn run_with_env(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> RunResult {
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("github_output");
+    fs::write(&output_path, "").unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tag_checker"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("GITHUB_OUTPUT", &output_path);
+    // Keep the runner's own GitHub actions environment from leaking into the tests
+    for name in ["GITHUB_HEAD_REF", "GITHUB_REF", "CI", "GITHUB_ACTIONS"] {
+        command.env_remove(name);
+    }
+    command.envs(env.iter().copied());
+    let output = command.output().unwrap();
+
+    let contents = fs::read_to_string(&output_path).unwrap();
+    let outputs = parse_outputs(&contents);
+
+    RunResult {
+        success: output.status.success(),
+        code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        output_file: contents,
+        outputs,
+    }
+}
+
+/// Parse a GitHub actions output file, including multiline `name<<DELIMITER` values
+fn parse_outputs(contents: &str) -> HashMap<String, String> {
+    let mut outputs = HashMap::new();
+    let mut lines = contents.lines();
+
+    while let Some(line) = lines.next() {
+        if let Some((key, delimiter)) = line.split_once("<<") {
+            let value = lines
+                .by_ref()
+                .take_while(|line| *line != delimiter)
+                .collect::<Vec<_>>()
+                .join("\n");
+            outputs.insert(key.to_string(), value);
+        } else if let Some((key, value)) = line.split_once('=') {
+            outputs.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    outputs
+}
+
+#[test]
+fn test_remote_source_reads_advertised_tags() {
+    // The "remote" has tags that were never fetched into the local clone
+    let remote = TestRepo::new();
+    remote.commit("Initial commit");
+    remote.tag("v1.0.0");
+    remote.commit("Second commit");
+    remote.tag("v1.1.0");
+
+    let local = TestRepo::new();
+    local.commit("Initial commit");
+    local.tag("v0.1.0");
+    local
+        .repo
+        .remote("origin", remote.path().to_str().unwrap())
+        .unwrap();
+
+    let result = run(
+        local.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v0.1.0");
+
+    let result = run(
+        local.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--source",
+            "remote",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert!(local.repo.find_reference("refs/tags/v1.1.0").is_err());
+}
+
+#[test]
+fn test_remote_source_missing_remote() {
+    let local = TestRepo::new();
+    local.commit("Initial commit");
+    local.tag("v0.1.0");
+
+    let result = run(
+        local.path(),
+        &["--release-branch", "main", "--source", "remote"],
+    );
+    assert!(!result.success);
+    assert!(result.stderr.contains("Remote 'origin' not found"));
+    assert!(result.stdout.contains("is the release branch"));
+}
+
+#[test]
+fn test_check_proposed_tag() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    // A free tag name succeeds
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--check", "v1.1.0"],
+    );
+    assert_eq!(result.code, Some(0), "{}", result.stderr);
+    assert_eq!(result.outputs["tag_exists"], "false");
+
+    // An existing tag name fails with the dedicated exit code
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--check", "v1.0.0"],
+    );
+    assert_eq!(result.code, Some(3), "{}", result.stderr);
+    assert_eq!(result.outputs["tag_exists"], "true");
+    assert!(result.stderr.contains("Tag v1.0.0 already exists"));
+
+    // A tag left out by the filters still exists
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--signed-only",
+            "--check",
+            "v1.0.0",
+        ],
+    );
+    assert_eq!(result.code, Some(3), "{}", result.stderr);
+    assert_eq!(result.outputs["tag_exists"], "true");
+}
+
+#[test]
+fn test_repo_path_overrides_working_directory() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.3");
+
+    // Run from a directory that is not inside any repository
+    let elsewhere = TempDir::new().unwrap();
+    let result = run(elsewhere.path(), &["--release-branch", "main"]);
+    assert!(!result.success);
+    assert!(result.stderr.contains("No git repository found"));
+
+    let result = run(
+        elsewhere.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--repo-path",
+            repo.path().to_str().unwrap(),
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.3");
+}
+
+#[test]
+fn test_docker_tags_output() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.4.2");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--docker-tags",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.4.2");
+    assert_eq!(result.outputs["docker_tags"], "1\n1.4\n1.4.2\nlatest");
+
+    // With any prerelease, the moving tag is the channel of the selected prerelease
+    repo.checkout_new_branch("develop");
+    repo.commit("Second commit");
+    repo.tag("v1.5.0-rc.1");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "beta",
+            "--any-prerelease",
+            "--docker-tags",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.5.0-rc.1");
+    assert_eq!(result.outputs["docker_tags"], "1.5.0-rc.1\nrc");
+}
+
+#[test]
+fn test_json_output_includes_tag_message() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.annotated_tag("v1.1.0", "Release notes\n\n- Added a feature\n");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--format",
+            "json",
+            "--include-message",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    let json: serde_json::Value = serde_json::from_str(&result.output_file).unwrap();
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["latest_tag"], "v1.1.0");
+    assert_eq!(
+        json["latest_tag_message"],
+        "Release notes\n\n- Added a feature"
+    );
+
+    // A lightweight tag has no message
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--max-version",
+            "1.0.0",
+            "--format",
+            "json",
+            "--include-message",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    let json: serde_json::Value = serde_json::from_str(&result.output_file).unwrap();
+    assert_eq!(json["latest_tag"], "v1.0.0");
+    assert_eq!(json["latest_tag_message"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_reachable_only_first_parent() {
+    // main:    c1 (v1.0.0) -- c2 -- merge
+    // feature:   \-- f1 (v1.1.0) --/
+    let repo = TestRepo::new();
+    let c1 = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let f1 = repo.commit_with_parents(Some("refs/heads/feature"), "Feature commit", &[c1]);
+    repo.tag_commit("v1.1.0", f1);
+    let c2 = repo.commit("Second commit");
+    repo.commit_with_parents(Some("HEAD"), "Merge feature", &[c2, f1]);
+
+    // An unrelated tag that is not reachable at all
+    let orphan = repo.commit_with_parents(None, "Orphan commit", &[]);
+    repo.tag_commit("v9.0.0", orphan);
+
+    let base_args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    let result = run(repo.path(), &base_args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v9.0.0");
+
+    let result = run(
+        repo.path(),
+        &[&base_args[..], &["--reachable-only"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+
+    let result = run(
+        repo.path(),
+        &[&base_args[..], &["--reachable-only", "--first-parent"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // A tag left out by the filters still exists
+    let result = run(
+        repo.path(),
+        &[&base_args[..], &["--reachable-only", "--check", "v9.0.0"]].concat(),
+    );
+    assert_eq!(result.code, Some(3), "{}", result.stderr);
+    assert_eq!(result.outputs["tag_exists"], "true");
+}
+
+#[test]
+fn test_empty_prerelease_suffix() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    // Stable mode does not use the suffix
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // Prerelease mode rejects it as a usage error
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "release",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "",
+        ],
+    );
+    assert_eq!(result.code, Some(2));
+    assert!(
+        result
+            .stderr
+            .contains("--prerelease-suffix must not be empty when selecting prerelease tags"),
+        "{}",
+        result.stderr
+    );
+
+    // Unless any prerelease is matched
+    repo.tag("v1.1.0-rc.1");
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "release",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "",
+            "--any-prerelease",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-rc.1");
+}
+
+#[test]
+fn test_cache_file() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let orphan = repo.commit_with_parents(None, "Orphan commit", &[]);
+    repo.tag_commit("v9.0.0", orphan);
+    let cache_dir = TempDir::new().unwrap();
+    let cache_file = cache_dir.path().join("tags.json");
+    let cache_file = cache_file.to_str().unwrap();
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--cache-file",
+        cache_file,
+    ];
+
+    // The cache is filled with every tag and used to check reachability
+    let result = run(repo.path(), &[&args[..], &["--reachable-only"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Resolved 2 of 2 tags"));
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+    let cached: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(cache_file).unwrap()).unwrap();
+    assert_eq!(cached["v9.0.0"]["sha"], orphan.to_string());
+
+    // A warm cache resolves nothing again
+    let result = run(repo.path(), &[&args[..], &["--reachable-only"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Resolved 0 of 2 tags"));
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // Without an option that resolves tag commits, the cache is not used
+    let result = run(repo.path(), &[&args[..], &["--tie-break", "name"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("--cache-file has nothing to speed up")
+    );
+    assert!(!result.stdout.contains("Resolved"));
+    assert_eq!(result.outputs["latest_tag"], "v9.0.0");
+}
+
+#[test]
+fn test_canonical_tag_output() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v01.2.3");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v01.2.3");
+    assert_eq!(result.outputs["latest_tag_canonical"], "1.2.3");
+}
+
+#[test]
+fn test_notes_source() {
+    let repo = TestRepo::new();
+    let signature = Signature::now("Test User", "test@example.com").unwrap();
+    for (message, note) in [
+        ("Initial commit", "v1.0.0"),
+        ("Second commit", "v1.2.0\nReleased by CI"),
+        ("Third commit", "v1.1.0"),
+    ] {
+        let commit = repo.commit(message);
+        repo.repo
+            .note(&signature, &signature, None, commit, note, false)
+            .unwrap();
+    }
+    // Tags are ignored when reading from notes
+    repo.tag("v2.0.0");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--source",
+            "notes",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0");
+
+    // A notes reference without any notes fails cleanly
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--source",
+            "notes",
+            "--notes-ref",
+            "refs/notes/versions",
+        ],
+    );
+    assert!(!result.success);
+    assert!(result.stderr.contains("refs/notes/versions"));
+}
+
+#[test]
+fn test_default_tag_when_no_tags_match() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(!result.success);
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--default-tag",
+            "v0.0.0",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v0.0.0");
+    assert_eq!(result.outputs["is_first_release"], "true");
+}
+
+#[test]
+fn test_latest_stable_tag_on_prerelease_branch() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.1.0");
+    repo.checkout_new_branch("develop");
+    repo.commit("Third commit");
+    repo.tag("v1.2.0-beta.1");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "beta",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("is not the release branch"));
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0-beta.1");
+    assert_eq!(result.outputs["latest_stable_tag"], "v1.1.0");
+
+    // Without any stable tag the output is empty
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.checkout_new_branch("develop");
+    repo.tag("v1.0.0-beta.1");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--prerelease-suffix",
+            "beta",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0-beta.1");
+    assert_eq!(result.outputs["latest_stable_tag"], "");
+}
+
+#[test]
+fn test_detached_head_uses_github_ref() {
+    let repo = TestRepo::new();
+    let commit = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.tag("v1.1.0-prerelease.1");
+    repo.repo.set_head_detached(commit).unwrap();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    // Without any hint the detached HEAD is treated as a prerelease branch, with a warning
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Warning: HEAD is detached"));
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+
+    // With GITHUB_REF naming the release branch, stable mode is used
+    let result = run_with_env(repo.path(), &args, &[("GITHUB_REF", "refs/heads/main")]);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_on_tag_mode() {
+    let repo = TestRepo::new();
+    let commit = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.tag("v1.1.0-prerelease.1");
+    repo.repo.set_head_detached(commit).unwrap();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    // A prerelease tag push is inferred to select prerelease tags, without a detached warning
+    let env = [("GITHUB_REF", "refs/tags/v1.1.0-prerelease.1")];
+    let result = run_with_env(
+        repo.path(),
+        &[&args[..], &["--on-tag-mode", "infer"]].concat(),
+        &env,
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains("Warning: HEAD is detached"));
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+
+    // A stable tag push is inferred to select stable tags
+    let env = [("GITHUB_REF", "refs/tags/v1.0.0")];
+    let result = run_with_env(
+        repo.path(),
+        &[&args[..], &["--on-tag-mode", "infer"]].concat(),
+        &env,
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // An explicit mode ignores the triggering tag
+    let result = run_with_env(
+        repo.path(),
+        &[&args[..], &["--on-tag-mode", "prerelease"]].concat(),
+        &env,
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+}
+
+#[test]
+fn test_emit_remote() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--emit-remote",
+    ];
+
+    // Without a remote the slug comes from the environment
+    let result = run_with_env(repo.path(), &args, &[("GITHUB_REPOSITORY", "owner/env")]);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["remote_url"], "");
+    assert_eq!(result.outputs["repo_slug"], "owner/env");
+
+    // The SSH and HTTPS forms of the remote give the same slug
+    for url in [
+        "git@github.com:owner/repo.git",
+        "https://github.com/owner/repo",
+    ] {
+        repo.repo.remote_delete("origin").ok();
+        repo.repo.remote("origin", url).unwrap();
+        let result = run_with_env(repo.path(), &args, &[("GITHUB_REPOSITORY", "owner/env")]);
+        assert!(result.success, "{}", result.stderr);
+        assert_eq!(result.outputs["remote_url"], url);
+        assert_eq!(result.outputs["repo_slug"], "owner/repo");
+    }
+}
+
+#[test]
+fn test_channels_subcommand() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    for tag in [
+        "v1.0.0",
+        "v1.1.0-alpha.1",
+        "v1.1.0-beta.1",
+        "v1.1.0-beta.2",
+        "v1.1.0-rc.1",
+    ] {
+        repo.tag(tag);
+    }
+
+    // The release branch is not needed to list the channels
+    let result = run(repo.path(), &["channels", "--tag-prefix", "v"]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("alpha: 1\nbeta: 2\nrc: 1\n"));
+    assert_eq!(
+        result.outputs["prerelease_channels"],
+        r#"{"alpha":1,"beta":2,"rc":1}"#
+    );
+}
+
+#[test]
+fn test_auto_prefix() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.tag("v1.1.0");
+    repo.tag("nightly");
+
+    let args = ["--release-branch", "main", "--auto-prefix"];
+
+    // Every version tag starts with v
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Detected the tag prefix \"v\""));
+    assert!(!result.stdout.contains("Warning"));
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+
+    // Mixed prefixes fall back to no prefix with a warning
+    repo.tag("1.2.0");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("Warning: No tag prefix is shared by all the version tags"),
+        "{}",
+        result.stdout
+    );
+    assert_eq!(result.outputs["latest_tag"], "1.2.0");
+}
+
+#[test]
+fn test_compare_subcommand() {
+    let dir = TempDir::new().unwrap();
+    let compare = |a: &str, b: &str| {
+        let result = run(dir.path(), &["compare", a, b, "--tag-prefix", "v"]);
+        assert!(result.success, "{}", result.stderr);
+        assert_eq!(result.stdout.trim(), result.outputs["comparison"]);
+        result.outputs["comparison"].clone()
+    };
+
+    assert_eq!(compare("v1.2.0", "v1.10.0"), "-1");
+    assert_eq!(compare("v1.2.0+build.1", "1.2.0+build.2"), "0");
+    assert_eq!(compare("v1.2.0", "v1.2.0-rc.1"), "1");
+
+    let result = run(
+        dir.path(),
+        &["compare", "v1.2.0", "latest", "--tag-prefix", "v"],
+    );
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains("latest is not a semantic version"),
+        "{}",
+        result.stderr
+    );
+
+    // Outside of GitHub actions the comparison is only printed
+    let output = Command::new(env!("CARGO_BIN_EXE_tag_checker"))
+        .args(["compare", "v1.2.0", "v1.10.0", "--tag-prefix", "v"])
+        .current_dir(dir.path())
+        .env_remove("GITHUB_OUTPUT")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "-1");
+}
+
+#[test]
+fn test_batch_subcommand() {
+    let valid = TestRepo::new();
+    valid.commit("Initial commit");
+    valid.tag("v1.0.0");
+    valid.tag("v1.1.0");
+    valid.tag("v1.2.0-rc.1");
+    let untagged = TestRepo::new();
+    untagged.commit("Initial commit");
+    untagged.tag("release-1");
+
+    let list = TempDir::new().unwrap();
+    let repos_file = list.path().join("repos.txt");
+    fs::write(
+        &repos_file,
+        format!(
+            "# Services\n{}\n\n{}\n",
+            valid.path().display(),
+            untagged.path().display()
+        ),
+    )
+    .unwrap();
+
+    // The batch runs from outside any repository
+    let result = run(
+        list.path(),
+        &[
+            "batch",
+            "--repos-file",
+            repos_file.to_str().unwrap(),
+            "--tag-prefix",
+            "v",
+            "--format",
+            "json",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    let outputs: serde_json::Value = serde_json::from_str(&result.output_file).unwrap();
+    let results = outputs["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["repo"], valid.path().to_str().unwrap());
+    assert_eq!(results[0]["latest_tag"], "v1.1.0");
+    assert!(results[0]["error"].is_null());
+    assert_eq!(results[1]["repo"], untagged.path().to_str().unwrap());
+    assert!(results[1]["latest_tag"].is_null());
+    assert!(
+        results[1]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("No tags found matching pattern")
+    );
+}
+
+#[test]
+fn test_lint_subcommand() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    for tag in ["v1.0.0", "v1.1", "V1.2.0", "v1.3.0-rc.1", "nightly"] {
+        repo.tag(tag);
+    }
+
+    let result = run(repo.path(), &["lint", "--tag-prefix", "v"]);
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("Tags do not follow the tag pattern: V1.2.0, v1.1"),
+        "{}",
+        result.stderr
+    );
+    assert_eq!(result.outputs["lint_violations"], "V1.2.0\nv1.1");
+
+    let result = run(repo.path(), &["lint", "--tag-prefix", "release-"]);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["lint_violations"], "");
+}
+
+#[test]
+fn test_require_on_release_branch() {
+    // main:    c1 (v1.0.0) -- c2
+    // feature:   \-- f1 (v1.1.0)
+    let repo = TestRepo::new();
+    let c1 = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let f1 = repo.commit_with_parents(Some("refs/heads/feature"), "Feature commit", &[c1]);
+    repo.tag_commit("v1.1.0", f1);
+    repo.commit("Second commit");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--require-on-release-branch",
+    ];
+
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("Tag v1.1.0 is not on the release branch (main)"),
+        "{}",
+        result.stderr
+    );
+
+    // Once the latest tag is on the release branch the check passes
+    repo.tag("v1.2.0");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0");
+}
+
+#[test]
+fn test_fail_on_tie() {
+    // Both tags have the same version and are on the same commit
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.2.3");
+    repo.tag("v01.2.3");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--fail-on-tie",
+    ];
+
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains(
+            "Tags v01.2.3, v1.2.3 have the same version and cannot be ordered by the date tie-break"
+        ),
+        "{}",
+        result.stderr
+    );
+
+    // A tie-break that can order the tags resolves the tie
+    let result = run(repo.path(), &[&args[..], &["--tie-break", "name"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.3");
+}
+
+#[test]
+fn test_ahead_behind_outputs() {
+    // main:    c1 (v1.0.0) -- c2 -- c3
+    // feature:   \-- f1 -- f2 (v1.1.0)
+    let repo = TestRepo::new();
+    let c1 = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let f1 = repo.commit_with_parents(Some("refs/heads/feature"), "Feature commit", &[c1]);
+    let f2 = repo.commit_with_parents(Some("refs/heads/feature"), "Second feature commit", &[f1]);
+    repo.tag_commit("v1.1.0", f2);
+    repo.commit("Second commit");
+    repo.commit("Third commit");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert_eq!(result.outputs["ahead"], "2");
+    assert_eq!(result.outputs["behind"], "2");
+
+    // HEAD has simply moved on from a tag in its history
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--max-version",
+            "1.0.0",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["ahead"], "2");
+    assert_eq!(result.outputs["behind"], "0");
+}
+
+#[test]
+fn test_manifest_version() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.0");
+
+    let manifests = [
+        (
+            "Cargo.toml",
+            "[package]\nname = \"tool\"\nversion = \"VERSION\"\n",
+        ),
+        (
+            "package.json",
+            "{\"name\": \"tool\", \"version\": \"VERSION\"}",
+        ),
+        (
+            "pyproject.toml",
+            "[project]\nname = \"tool\"\nversion = \"VERSION\"\n",
+        ),
+    ];
+    for (file_name, template) in manifests {
+        for (version, ahead) in [("1.3.0", "true"), ("1.2.0", "false"), ("1.1.0", "false")] {
+            let path = repo.path().join(file_name);
+            fs::write(&path, template.replace("VERSION", version)).unwrap();
+
+            let result = run(
+                repo.path(),
+                &[
+                    "--release-branch",
+                    "main",
+                    "--tag-prefix",
+                    "v",
+                    "--manifest",
+                    file_name,
+                ],
+            );
+            assert!(result.success, "{file_name}: {}", result.stderr);
+            assert_eq!(result.outputs["manifest_version"], version, "{file_name}");
+            assert_eq!(
+                result.outputs["manifest_ahead"], ahead,
+                "{file_name} {version}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_target_branch() {
+    // main:    c1 (v1.0.0) -- c2 (v1.1.0)
+    // develop:   \-- d1 (v1.1.0-prerelease.1)
+    let repo = TestRepo::new();
+    let c1 = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let d1 = repo.commit_with_parents(Some("refs/heads/develop"), "Develop commit", &[c1]);
+    repo.tag_commit("v1.1.0-prerelease.1", d1);
+    repo.commit("Second commit");
+    repo.tag("v1.1.0");
+
+    // A release pull request from a branch off main into main
+    repo.checkout_new_branch("release-1.2");
+    repo.commit("Prepare release");
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--target-branch", "main"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("Target branch (main) is the release branch and HEAD contains its tip")
+    );
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+
+    // A feature pull request from a branch off develop into develop
+    repo.repo.set_head("refs/heads/develop").unwrap();
+    repo.checkout_new_branch("feature");
+    repo.commit("Add feature");
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--target-branch", "develop"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("Target branch (develop) is not the release branch (main)")
+    );
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+
+    // A pull request into main from a branch that has not merged the tip of main
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--target-branch", "main"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("HEAD does not contain the tip of the target branch (main)")
+    );
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+}
+
+#[test]
+fn test_check_monotonic() {
+    // The version went backwards on the newest commit
+    let repo = TestRepo::new();
+    repo.commit_at("Initial commit", 1_000);
+    repo.tag("v1.0.0");
+    repo.commit_at("Second commit", 2_000);
+    repo.tag("v1.2.0");
+    repo.commit_at("Third commit", 3_000);
+    repo.tag("v1.1.0");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    let result = run(repo.path(), &[&args[..], &["--check-monotonic"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("Warning: v1.1.0 is on a newer commit than v1.2.0 but has a lower version"),
+        "{}",
+        result.stdout
+    );
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0");
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-monotonic=error"]].concat(),
+    );
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains("v1.1.0 after v1.2.0"),
+        "{}",
+        result.stderr
+    );
+
+    // Check that violations can be reported as workflow annotations
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-monotonic=error", "--annotations"]].concat(),
+    );
+    assert!(!result.success);
+    assert!(
+        result
+            .stdout
+            .contains("::error::Versions go backwards over time: v1.1.0 after v1.2.0\n"),
+        "{}",
+        result.stdout
+    );
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-monotonic", "--annotations"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result.stdout.contains(
+            "::warning::v1.1.0 is on a newer commit than v1.2.0 but has a lower version."
+        ),
+        "{}",
+        result.stdout
+    );
+
+    // Without the check, or with increasing versions, nothing is reported
+    let result = run(repo.path(), &args);
+    assert!(!result.stdout.contains("Warning"));
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-monotonic", "--max-version", "1.1.0"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains("Warning"), "{}", result.stdout);
+}
+
+#[test]
+fn test_check_sequential_prereleases() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v0.9.0");
+    repo.tag("v1.0.0-rc.1");
+    repo.commit("Second commit");
+    repo.tag("v1.0.0-rc.3");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--prerelease-suffix",
+        "rc",
+    ];
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-sequential-prereleases"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("Warning: v1.0.0-rc.2 is missing from its prerelease series."),
+        "{}",
+        result.stdout
+    );
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-sequential-prereleases=error"]].concat(),
+    );
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains("missing: v1.0.0-rc.2"),
+        "{}",
+        result.stderr
+    );
+
+    repo.tag("v1.0.0-rc.2");
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--check-sequential-prereleases=error"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v0.9.0");
+}
+
+#[test]
+fn test_quiet_no_match() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("release-1");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(result.stderr.contains("No tags found matching pattern"));
+
+    let result = run(repo.path(), &[&args[..], &["--quiet-no-match"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.output_file, "latest_tag=\n");
+
+    // Matching tags are selected as usual
+    repo.tag("v1.0.0");
+    let result = run(repo.path(), &[&args[..], &["--quiet-no-match"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+}
+
+#[cfg(feature = "signing-tests")]
+#[test]
+fn test_signed_only_and_verify_key() {
+    /// Generate an SSH key pair in `dir`, returning the path of the private key
+    fn generate_key(dir: &Path, name: &str) -> std::path::PathBuf {
+        let key = dir.join(name);
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        key
+    }
+
+    let repo = TestRepo::new();
+    let keys = TempDir::new().unwrap();
+    let signing_key = generate_key(keys.path(), "signing");
+    let other_key = generate_key(keys.path(), "other");
+
+    repo.commit("Initial commit");
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Test User",
+            "-c",
+            "user.email=test@example.com",
+        ])
+        .args(["-c", "gpg.format=ssh", "-c"])
+        .arg(format!("user.signingkey={}", signing_key.display()))
+        .args(["tag", "-s", "v1.0.0", "-m", "Release 1.0.0"])
+        .current_dir(repo.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    repo.commit("Second commit");
+    repo.annotated_tag("v1.1.0", "Unsigned release");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    // Unsigned tags are skipped
+    let result = run(repo.path(), &[&args[..], &["--signed-only"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // The signature verifies against the matching key only
+    let public_key = signing_key.with_extension("pub");
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &[
+                "--signed-only",
+                "--verify-key",
+                public_key.to_str().unwrap(),
+            ],
+        ]
+        .concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Verified the signature of v1.0.0"));
+
+    let public_key = other_key.with_extension("pub");
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &[
+                "--signed-only",
+                "--verify-key",
+                public_key.to_str().unwrap(),
+            ],
+        ]
+        .concat(),
+    );
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("The signature of tag v1.0.0 could not be verified"),
+        "{}",
+        result.stderr
+    );
+}
+
+#[cfg(all(feature = "signing-tests", feature = "signer-output"))]
+#[test]
+fn test_emit_signer() {
+    let repo = TestRepo::new();
+    let keys = TempDir::new().unwrap();
+    let signing_key = keys.path().join("signing");
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&signing_key)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let public_key = signing_key.with_extension("pub");
+    let listing = Command::new("ssh-keygen")
+        .arg("-lf")
+        .arg(&public_key)
+        .output()
+        .unwrap();
+    let fingerprint = String::from_utf8_lossy(&listing.stdout)
+        .split_whitespace()
+        .nth(1)
+        .unwrap()
+        .to_string();
+
+    repo.commit("Initial commit");
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Test User",
+            "-c",
+            "user.email=test@example.com",
+        ])
+        .args(["-c", "gpg.format=ssh", "-c"])
+        .arg(format!("user.signingkey={}", signing_key.display()))
+        .args(["tag", "-s", "v1.0.0", "-m", "Release 1.0.0"])
+        .current_dir(repo.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    // The key is named whether or not it is trusted
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--signed-only", "--emit-signer"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["signer"], fingerprint);
+
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &[
+                "--verify-key",
+                public_key.to_str().unwrap(),
+                "--emit-signer",
+            ],
+        ]
+        .concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["signer"], fingerprint);
+
+    // An unsigned tag has no signer
+    repo.commit("Second commit");
+    repo.annotated_tag("v1.1.0", "Unsigned release");
+    let result = run(repo.path(), &[&args[..], &["--emit-signer"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert_eq!(result.outputs["signer"], "");
+}
+
+#[test]
+fn test_commit_type_counts() {
+    let repo = TestRepo::new();
+    repo.commit("feat: initial feature");
+    repo.tag("v1.0.0");
+    for message in [
+        "feat(cli): add a flag",
+        "fix: handle missing tags",
+        "feat!: rename the outputs",
+        "chore: tidy up\n\nBREAKING CHANGE: drops Rust 1.80",
+        "Update README",
+    ] {
+        repo.commit(message);
+    }
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    // The history is only scanned when asked for
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.outputs.contains_key("feat_count"));
+
+    let result = run(repo.path(), &[&args[..], &["--commit-counts"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["feat_count"], "2");
+    assert_eq!(result.outputs["fix_count"], "1");
+    assert_eq!(result.outputs["breaking_count"], "2");
+}
+
+#[test]
+fn test_range_bump() {
+    // main:       c1 (v1.0.0) -- c2 feat (v1.1.0) -- c3 feat!
+    // backport-1.0:   \-- b1 fix (v1.0.1) -- b2 fix
+    let repo = TestRepo::new();
+    let c1 = repo.commit("chore: initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("feat: add channels");
+    repo.tag("v1.1.0");
+    repo.commit("feat!: rename the outputs");
+    let b1 = repo.commit_with_parents(Some("refs/heads/backport-1.0"), "fix: handle HEAD", &[c1]);
+    repo.tag_commit("v1.0.1", b1);
+    repo.commit_with_parents(Some("refs/heads/backport-1.0"), "fix: typo", &[b1]);
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--range-bump", "v1.0.1..backport-1.0"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["range_bump"], "patch");
+
+    // Check that --bump auto uses the range rather than the commits since the latest tag
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &["--range-bump", "v1.0.0..v1.1.0", "--bump", "auto"],
+        ]
+        .concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["range_bump"], "minor");
+    assert_eq!(result.outputs["next_tag"], "v1.2.0");
+    let result = run(repo.path(), &[&args[..], &["--bump", "auto"]].concat());
+    assert_eq!(result.outputs["next_tag"], "v2.0.0");
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--range-bump", "v1.0.0..missing"]].concat(),
+    );
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("Failed to resolve the range v1.0.0..missing")
+    );
+}
+
+#[test]
+fn test_output_prefix() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.annotated_tag("v1.0.0", "Release 1.0.0\n\nFirst release");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--docker-tags",
+        "--include-message",
+    ];
+    let plain = run(repo.path(), &args);
+    let prefixed = run(
+        repo.path(),
+        &[&args[..], &["--output-prefix", "linux_"]].concat(),
+    );
+    assert!(prefixed.success, "{}", prefixed.stderr);
+
+    assert_eq!(prefixed.outputs.len(), plain.outputs.len());
+    for (name, value) in &plain.outputs {
+        assert_eq!(prefixed.outputs[&format!("linux_{name}")], *value);
+    }
+    assert_eq!(prefixed.outputs["linux_latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_output_prefix_shared_file() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    // Earlier steps and the runner itself may have written to the output file already
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("github_output");
+    fs::write(&output_path, "earlier=kept\n").unwrap();
+    let github_output = output_path.to_str().unwrap();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    for prefix in ["linux_", "macos_"] {
+        let result = run_with_env(
+            repo.path(),
+            &[&args[..], &["--output-prefix", prefix]].concat(),
+            &[("GITHUB_OUTPUT", github_output)],
+        );
+        assert!(result.success, "{}", result.stderr);
+    }
+
+    let outputs = parse_outputs(&fs::read_to_string(&output_path).unwrap());
+    assert_eq!(outputs["earlier"], "kept");
+    assert_eq!(outputs["linux_latest_tag"], "v1.0.0");
+    assert_eq!(outputs["macos_latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_bare_repository() {
+    let repo = TestRepo::new_bare();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.1.0-prerelease.1");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // Check that the branch can be given when HEAD names a branch that does not exist
+    repo.repo.set_head("refs/heads/master").unwrap();
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(result.stderr.contains("--branch"), "{}", result.stderr);
+
+    let result = run(repo.path(), &[&args[..], &["--branch", "develop"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+}
+
+#[test]
+fn test_create_and_preview_tag() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.0");
+    let head = repo.commit("Fix a bug");
+    let mut config = repo.repo.config().unwrap();
+    config.set_str("user.name", "Release Bot").unwrap();
+    config.set_str("user.email", "bot@example.com").unwrap();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &[&args[..], &["--preview-tag"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    for line in [
+        "Tag: v1.2.1".to_string(),
+        format!("Target: {head} (Fix a bug)"),
+        "Tagger: Release Bot <bot@example.com>".to_string(),
+        "Message: Release v1.2.1".to_string(),
+    ] {
+        assert!(result.stdout.contains(&line), "{}", result.stdout);
+    }
+    assert!(repo.repo.find_reference("refs/tags/v1.2.1").is_err());
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--create-tag", "--bump", "minor", "--yes"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    let tag = repo
+        .repo
+        .find_reference("refs/tags/v1.3.0")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(tag.target_id(), head);
+    assert_eq!(tag.message(), Some("Release v1.3.0"));
+    assert_eq!(result.outputs["next_tag"], "v1.3.0");
+}
+
+#[test]
+fn test_create_tag_confirmation() {
+    let remote = TestRepo::new_bare();
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Fix a bug");
+    repo.repo
+        .remote("origin", remote.path().to_str().unwrap())
+        .unwrap();
+    let mut config = repo.repo.config().unwrap();
+    config.set_str("user.name", "Release Bot").unwrap();
+    config.set_str("user.email", "bot@example.com").unwrap();
+
+    // Outside CI nobody answers the prompt on the test's empty stdin, so nothing is created
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--create-tag",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result.stdout.contains("Create tag v1.0.1? [y/N]"),
+        "{}",
+        result.stdout
+    );
+    assert!(
+        result.stderr.contains("Tag creation cancelled"),
+        "{}",
+        result.stderr
+    );
+    assert!(repo.repo.find_reference("refs/tags/v1.0.1").is_err());
+
+    // In CI the tag is created and pushed without asking
+    let result = run_with_env(
+        repo.path(),
+        &[&args[..], &["--push"]].concat(),
+        &[("CI", "true")],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains("[y/N]"));
+    assert!(repo.repo.find_reference("refs/tags/v1.0.1").is_ok());
+    assert!(remote.repo.find_reference("refs/tags/v1.0.1").is_ok());
+
+    // Locally --yes skips the prompt
+    repo.commit("Fix another bug");
+    let result = run(repo.path(), &[&args[..], &["--yes"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains("[y/N]"));
+    assert!(repo.repo.find_reference("refs/tags/v1.0.2").is_ok());
+    assert!(remote.repo.find_reference("refs/tags/v1.0.2").is_err());
+}
+
+#[test]
+fn test_breaking_since_stable() {
+    let repo = TestRepo::new();
+    repo.commit("feat: initial feature");
+    repo.tag("v1.0.0");
+    repo.commit("fix: handle missing tags");
+    repo.tag("v1.1.0-prerelease.1");
+
+    // On a prerelease branch the range still starts at the latest stable tag
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--branch",
+        "develop",
+        "--commit-counts",
+        "--breaking-since-stable",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+    assert_eq!(result.outputs["breaking_since_stable"], "false");
+
+    repo.commit("refactor: rename outputs\n\nBREAKING CHANGE: tag is now latest_tag");
+    repo.tag("v1.1.0-prerelease.2");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["breaking_since_stable"], "true");
+    // The breaking change is already in the latest prerelease
+    assert_eq!(result.outputs["breaking_count"], "0");
+
+    // A breaking change past the scan limit is missed, with a warning
+    repo.commit("fix: handle empty input");
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--max-scan-commits", "1"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["breaking_since_stable"], "false");
+    assert!(
+        result
+            .stdout
+            .contains("Only the latest 1 commits since v1.0.0 were scanned for breaking changes"),
+        "{}",
+        result.stdout
+    );
+
+    // Nothing is scanned unless asked for
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.outputs.contains_key("breaking_since_stable"));
+}
+
+#[test]
+fn test_auto_bump_before_1_0() {
+    let repo = TestRepo::new();
+    repo.commit("feat: initial feature");
+    repo.tag("v0.3.4");
+    repo.commit("fix: handle missing tags");
+    repo.commit("feat!: rename the outputs");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--bump",
+        "auto",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["next_tag"], "v0.4.0");
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--strict-semver-bump"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["next_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_contributors() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let head = repo.repo.head().unwrap().target().unwrap();
+    let mut parent = head;
+    for (name, email) in [
+        ("Zoe Writer", "zoe@example.com"),
+        ("Alex Coder", "alex@example.com"),
+        ("Zoe W.", "ZOE@example.com"),
+        ("Bo Reviewer", "bo@example.com"),
+    ] {
+        let signature = Signature::now(name, email).unwrap();
+        parent = repo.commit_with_signature(Some("HEAD"), "Change", &[parent], &signature);
+    }
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &[&args[..], &["--contributors"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["contributors"],
+        "Alex Coder <alex@example.com>\nBo Reviewer <bo@example.com>\nZoe W. <ZOE@example.com>"
+    );
+
+    let result = run(repo.path(), &args);
+    assert!(!result.outputs.contains_key("contributors"));
+}
+
+#[test]
+fn test_latest_tag_age_days() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let repo = TestRepo::new();
+    // Backdated by 10 days and an hour so the run can take a while without changing the age
+    repo.commit_at("Initial commit", now - 10 * 24 * 60 * 60 - 60 * 60);
+    repo.tag("v1.0.0");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag_age_days"], "10");
+}
+
+#[test]
+fn test_latest_tag_sha() {
+    let repo = TestRepo::new();
+    let head = repo.commit("Initial commit");
+    repo.annotated_tag("v1.0.0", "Release 1.0.0");
+    let full = head.to_string();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag_sha"], full);
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--short-sha", "--long-sha"]].concat(),
+    );
+    assert_eq!(result.outputs["latest_tag_sha"], full);
+
+    let result = run(repo.path(), &[&args[..], &["--short-sha"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag_sha"], full[..7]);
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--short-sha", "--abbrev", "12"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag_sha"], full[..12]);
+
+    let result = run(repo.path(), &[&args[..], &["--abbrev", "12"]].concat());
+    assert!(!result.success);
+}
+
+#[test]
+fn test_on_tag() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let tagged = repo.commit("Second commit");
+    repo.annotated_tag("v1.1.0", "Release 1.1.0");
+    repo.commit("Third commit");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--branch",
+        "main",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert_eq!(result.outputs["on_tag"], "false");
+
+    // A tag-triggered build checks out the tagged commit
+    repo.repo.set_head_detached(tagged).unwrap();
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert_eq!(result.outputs["on_tag"], "true");
+}
+
+#[test]
+fn test_pattern_output() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("pkg.core/v1.0.0");
+    repo.tag("pkg.core/v1.1.0-preXrelease.1");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "pkg.core/v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["pattern"],
+        r"^pkg\.core/v\d+\.\d+\.\d+(\+[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?$"
+    );
+
+    // Check that the suffix is escaped too, so its `.` does not match the `X`
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &["--branch", "develop", "--prerelease-suffix", "pre.release"],
+        ]
+        .concat(),
+    );
+    assert!(!result.success);
+    // The error is printed in its debug form, which doubles the backslashes
+    let pattern =
+        r"^pkg\.core/v\d+\.\d+\.\d+-pre\.release\.\d+(\+[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?$";
+    assert!(
+        result.stderr.contains(&pattern.replace('\\', "\\\\")),
+        "{}",
+        result.stderr
+    );
+}
+
+#[test]
+fn test_latest_tag_subject() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Add the lint subcommand\r\nwith a wrapped subject\n\nAnd a body");
+    repo.annotated_tag("v1.1.0", "Release 1.1.0");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["latest_tag_subject"],
+        "Add the lint subcommand"
+    );
+    assert!(
+        result
+            .output_file
+            .contains("latest_tag_subject=Add the lint subcommand\n")
+    );
+}
+
+#[test]
+fn test_suffix_from_branch() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.checkout_new_branch("feature/Login");
+    repo.commit("Add the login page");
+    repo.tag("v1.1.0-feature-login.1");
+    repo.tag("v1.1.0-prerelease.3");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--suffix-from-branch",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["prerelease_suffix"], "feature-login");
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-feature-login.1");
+
+    let result = run(repo.path(), &[&args[..], &["--next-prerelease"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["next_prerelease_tag"],
+        "v1.1.0-feature-login.2"
+    );
+
+    // Check that the release branch keeps selecting stable tags
+    let result = run(repo.path(), &[&args[..], &["--branch", "main"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["prerelease_suffix"], "prerelease");
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_compare_branch() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.checkout_new_branch("develop");
+    repo.commit("Add a feature");
+    repo.tag("v1.1.0-prerelease.1");
+    repo.tag("v1.1.0");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--prerelease-suffix",
+        "prerelease",
+    ];
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--compare-branch", "main"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0-prerelease.1");
+    // v1.1.0 is not on main
+    assert_eq!(result.outputs["compare_latest_tag"], "v1.0.0");
+    assert_eq!(result.outputs["ahead_of_compare"], "true");
+
+    // Check that the other branch selects tags by its own mode
+    repo.repo.set_head("refs/heads/main").unwrap();
+    let result = run(
+        repo.path(),
+        &[
+            &args[..],
+            &["--compare-branch", "develop", "--reachable-only"],
+        ]
+        .concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+    assert_eq!(result.outputs["compare_latest_tag"], "v1.1.0-prerelease.1");
+    assert_eq!(result.outputs["ahead_of_compare"], "false");
+
+    // A branch without any matching tag leaves the comparison empty
+    repo.commit_with_parents(Some("refs/heads/orphan"), "Orphan commit", &[]);
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--compare-branch", "orphan"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("No tag on orphan matches the filters")
+    );
+    assert_eq!(result.outputs["compare_latest_tag"], "");
+    assert_eq!(result.outputs["ahead_of_compare"], "");
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--compare-branch", "missing"]].concat(),
+    );
+    assert!(!result.success);
+    assert!(result.stderr.contains("Branch missing not found"));
+}
+
+#[test]
+fn test_promotion_check() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.0");
+    repo.commit("Add a feature");
+    repo.tag("v1.3.0-rc.4");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--prerelease-suffix",
+        "rc",
+        "--promotion-check",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0");
+    assert_eq!(result.outputs["promotable"], "true");
+
+    // Check that a commit after the prerelease blocks the promotion
+    repo.commit("Fix a typo");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["promotable"], "false");
+    assert!(
+        result
+            .stdout
+            .contains("HEAD is 1 commit(s) ahead of v1.3.0-rc.4")
+    );
+}
+
+#[test]
+fn test_require_annotated() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.annotated_tag("v1.0.0", "Release 1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.1.0");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--require-annotated",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains(
+            "Tag v1.1.0 is a lightweight tag, but --require-annotated requires an annotated tag"
+        ),
+        "{}",
+        result.stderr
+    );
+
+    repo.repo.tag_delete("v1.1.0").unwrap();
+    repo.annotated_tag("v1.1.0", "Release 1.1.0");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+}
+
+#[test]
+fn test_build_number() {
+    let repo = TestRepo::new();
+    let first = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--build-number",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["build_number"], "2");
+    assert_eq!(run(repo.path(), &args).outputs["build_number"], "2");
+
+    // Check that a merge counts the commits of both parents once
+    let side = repo.commit_with_parents(None, "Side commit", &[first]);
+    let head = repo.repo.head().unwrap().target().unwrap();
+    repo.commit_with_parents(Some("HEAD"), "Merge the side branch", &[head, side]);
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["build_number"], "4");
+}
+
+#[test]
+fn test_recommended_tag() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.0");
+    repo.commit("feat: add the recommended tag");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--prerelease-suffix",
+        "rc",
+        "--bump",
+        "auto",
+        "--recommended-tag",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["recommended_tag"], "v1.3.0");
+
+    repo.checkout_new_branch("develop");
+    repo.tag("v1.3.0-rc.1");
+    repo.commit("fix: handle empty tags");
+    repo.tag("v1.3.0-rc.2");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.3.0-rc.2");
+    assert_eq!(result.outputs["recommended_tag"], "v1.3.0-rc.3");
+}
+
+#[test]
+fn test_exclude_head_tag() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.1.0");
+    repo.commit("Third commit");
+    repo.tag("v1.2.0");
+    repo.tag("v1.2.1");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["previous_tag"], "v1.2.0");
+
+    // Check that neither tag on HEAD counts as the previous release
+    let result = run(repo.path(), &[&args[..], &["--exclude-head-tag"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.1");
+    assert_eq!(result.outputs["previous_tag"], "v1.1.0");
+    assert_eq!(result.outputs["previous_stable"], "v1.1.0");
+
+    // Check that with an untagged HEAD the previous tag is still the one before the latest
+    repo.commit("Fourth commit");
+    let result = run(repo.path(), &[&args[..], &["--exclude-head-tag"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.1");
+    assert_eq!(result.outputs["previous_tag"], "v1.2.0");
+    assert_eq!(result.outputs["previous_stable"], "v1.2.0");
+}
+
+#[test]
+fn test_warnings_json() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    for tag in ["v1.0.0", "v1.1.0", "v999.0.0"] {
+        repo.tag(tag);
+    }
+    let yanked = TempDir::new().unwrap();
+    let yanked_file = yanked.path().join("yanked.txt");
+    fs::write(&yanked_file, "1.1.0\n").unwrap();
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--max-version",
+            "100.0.0",
+            "--yanked-file",
+            yanked_file.to_str().unwrap(),
+            "--warnings-json",
+            "--format",
+            "json",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    let outputs: serde_json::Value = serde_json::from_str(&result.output_file).unwrap();
+    assert_eq!(outputs["latest_tag"], "v1.0.0");
+    assert_eq!(
+        outputs["warnings"],
+        serde_json::json!([
+            {
+                "kind": "skipped_tag",
+                "tag": "v1.1.0",
+                "message": "Skipping tag v1.1.0: version 1.1.0 is yanked"
+            },
+            {
+                "kind": "skipped_tag",
+                "tag": "v999.0.0",
+                "message": "Skipping tag v999.0.0: version is above the maximum version (100.0.0)"
+            }
+        ])
+    );
+}
+
+#[test]
+fn test_author_allowlist() {
+    let repo = TestRepo::new();
+    let maintainer = Signature::new(
+        "Alice Maintainer",
+        "alice@example.com",
+        &Time::new(1_000, 0),
+    )
+    .unwrap();
+    let contributor =
+        Signature::new("Mallory", "mallory@example.com", &Time::new(2_000, 0)).unwrap();
+    let first = repo.commit_with_signature(Some("HEAD"), "Initial commit", &[], &maintainer);
+    repo.tag("v1.0.0");
+    let second = repo.commit_with_signature(Some("HEAD"), "Second commit", &[first], &contributor);
+    repo.tag("v1.1.0");
+    repo.commit_with_signature(Some("HEAD"), "Third commit", &[second], &maintainer);
+    repo.tag("v1.0.1");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+
+    // Check that emails match case-insensitively and names exactly
+    for allowlist in ["ALICE@example.com", "Bob,Alice Maintainer"] {
+        let result = run(
+            repo.path(),
+            &[&args[..], &["--author-allowlist", allowlist]].concat(),
+        );
+        assert!(result.success, "{}", result.stderr);
+        assert_eq!(result.outputs["latest_tag"], "v1.0.1");
+        assert!(
+            result
+                .stdout
+                .contains("Skipping tag v1.1.0: its commit is not by an allowed author")
+        );
+    }
+}
+
+#[test]
+fn test_list() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    for tag in [
+        "v1.10.0",
+        "v1.2.0",
+        "v01.9.0+b1",
+        "v1.10.0-prerelease.1",
+        "other",
+    ] {
+        repo.tag(tag);
+    }
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v", "--list"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["tags"], "v1.10.0\nv01.9.0+b1\nv1.2.0");
+
+    // Check that the sort keys are in descending semver order like the tags
+    let result = run(repo.path(), &[&args[..], &["--emit-sort-key"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["tags"],
+        "v1.10.0\t1.10.0\nv01.9.0+b1\t1.9.0+b1\nv1.2.0\t1.2.0"
+    );
+    let keys = result.outputs["tags"]
+        .lines()
+        .map(|line| semver::Version::parse(line.split_once('\t').unwrap().1).unwrap())
+        .collect::<Vec<_>>();
+    assert!(keys.windows(2).all(|pair| pair[0] > pair[1]));
+}
+
+#[test]
+fn test_shallow_clone_warning() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("Second commit");
+    repo.tag("v1.1.0");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains("shallow clone"));
+
+    let clone = TempDir::new().unwrap();
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--depth", "1"])
+        .arg(format!("file://{}", repo.path().display()))
+        .arg(clone.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let result = run(clone.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result.stdout.contains(
+            "The repository is a shallow clone, so older tags may be missing and the latest tag may be outdated."
+        ),
+        "{}",
+        result.stdout
+    );
+    assert!(result.stdout.contains("fetch-depth: 0"));
+}
+
+#[test]
+fn test_latest_tag_ref() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("release/v1.2.3");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "release/v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag_ref"], "refs/tags/release/v1.2.3");
+
+    // Check that a default tag that does not exist has no ref
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--default-tag",
+            "v0.0.0",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v0.0.0");
+    assert_eq!(result.outputs["latest_tag_ref"], "");
+}
+
+#[test]
+fn test_output_prefix_override() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.2.3");
+    repo.tag("ver1.2.2");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v,ver",
+            "--output-prefix-override",
+            "release-",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Latest tag found: v1.2.3"));
+    assert_eq!(result.outputs["latest_tag"], "release-1.2.3");
+    assert_eq!(result.outputs["latest_tag_ref"], "refs/tags/v1.2.3");
+}
+
+#[test]
+fn test_per_branch() {
+    let repo = TestRepo::new();
+    let first = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.checkout_new_branch("release/1.x");
+    repo.commit("Fix the 1.x line");
+    repo.tag("v1.0.1");
+    repo.tag("v1.1.0-prerelease.1");
+
+    repo.repo.set_head("refs/heads/main").unwrap();
+    let second = repo.commit_with_parents(Some("HEAD"), "Start 2.0", &[first]);
+    repo.tag("v2.0.0");
+    repo.checkout_new_branch("release/2.x");
+    repo.checkout_new_branch("develop");
+    repo.tag_commit(
+        "v2.1.0",
+        repo.commit_with_parents(None, "Unmerged", &[second]),
+    );
+    // A remote tracking branch is included, without the remote name
+    repo.repo
+        .reference("refs/remotes/origin/release/0.x", first, false, "")
+        .unwrap();
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--per-branch",
+            "release/*",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    let per_branch: serde_json::Value =
+        serde_json::from_str(&result.outputs["per_branch"]).unwrap();
+    assert_eq!(
+        per_branch,
+        serde_json::json!({
+            "release/0.x": "v1.0.0",
+            "release/1.x": "v1.0.1",
+            "release/2.x": "v2.0.0"
+        })
+    );
+}
+
+#[test]
+fn test_require_resolvable() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    // A mirrored tag whose commit was never fetched
+    fs::write(
+        repo.path().join(".git/refs/tags/v2.0.0"),
+        "0123456789abcdef0123456789abcdef01234567\n",
+    )
+    .unwrap();
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v2.0.0");
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--require-resolvable"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+    assert!(
+        result
+            .stdout
+            .contains("Skipping tag v2.0.0: its commit is not in the repository")
+    );
+}
+
+#[test]
+fn test_clean_release() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["clean_release"], "true");
+
+    // Check that an untracked file does not count, but a staged one does
+    fs::write(repo.path().join("notes.txt"), "draft").unwrap();
+    assert_eq!(run(repo.path(), &args).outputs["clean_release"], "true");
+    let mut index = repo.repo.index().unwrap();
+    index.add_path(Path::new("notes.txt")).unwrap();
+    index.write().unwrap();
+    assert_eq!(run(repo.path(), &args).outputs["clean_release"], "false");
+    index.clear().unwrap();
+    index.write().unwrap();
+
+    repo.commit("Second commit");
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["clean_release"], "false");
+}
+
+#[test]
+fn test_max_scan_commits() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.commit("feat!: drop the old outputs");
+    for n in 0..3 {
+        repo.commit(&format!("fix: handle case {n}"));
+    }
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--bump",
+        "auto",
+        "--commit-counts",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["next_tag"], "v2.0.0");
+    assert!(!result.stdout.contains("were scanned"));
+
+    // Check that the breaking change past the limit is missed, with a warning
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--max-scan-commits", "3"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["fix_count"], "3");
+    assert_eq!(result.outputs["breaking_count"], "0");
+    assert_eq!(result.outputs["next_tag"], "v1.0.1");
+    assert!(result.stdout.contains(
+        "Only the latest 3 commits since v1.0.0 were scanned for conventional commits, so the bump may be too small."
+    ));
+
+    // Check that exactly the limit is not truncated
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--max-scan-commits", "4"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["next_tag"], "v2.0.0");
+    assert!(!result.stdout.contains("were scanned"));
+}
+
+#[test]
+fn test_commit_list() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let fix = repo.commit("fix: handle empty input\n\nThe body is left out");
+    let feat = repo.commit("feat: add arrays");
+    let short = |id: Oid| {
+        let object = repo.repo.find_object(id, None).unwrap();
+        object.short_id().unwrap().as_str().unwrap().to_string()
+    };
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--commit-list",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["commits"],
+        format!(
+            "{} feat: add arrays\n{} fix: handle empty input",
+            short(feat),
+            short(fix)
+        )
+    );
+
+    // The list stops at the scan limit
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--commit-list",
+            "--max-scan-commits",
+            "1",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(
+        result.outputs["commits"],
+        format!("{} feat: add arrays", short(feat))
+    );
+}
+
+#[test]
+fn test_sort_by_date() {
+    let repo = TestRepo::new();
+    repo.commit_at("Initial commit", 1_700_000_000);
+    repo.tag("v2.0.0");
+    repo.commit_at("Re-tagged an old release", 1_700_100_000);
+    repo.tag("v1.4.0");
+    repo.tag("nightly");
+
+    // The newest tag wins despite its lower version
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--sort-by",
+            "date",
+            "--scheme",
+            "none",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.4.0");
+    assert_eq!(result.outputs["latest_tag_ref"], "refs/tags/v1.4.0");
+
+    // Without a prefix any tag matches, with ties broken by name
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--sort-by",
+            "date",
+            "--scheme",
+            "none",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.4.0");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--scheme", "none"],
+    );
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains("needs --sort-by date"),
+        "{}",
+        result.stderr
+    );
+}
+
+#[test]
+fn test_semver_outranks_alias() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("1.2.3");
+    repo.tag("latest");
+
+    // Both tags were created with the commit, and the alias has the greater name
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--sort-by",
+            "date",
+            "--scheme",
+            "none",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "1.2.3");
+}
+
+#[test]
+fn test_release_metadata() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.1.0");
+    repo.commit("Second commit");
+    repo.tag("v1.2.0");
+    repo.commit("fix: handle empty input");
+    let feat = repo.commit("feat: add arrays");
+
+    let output_dir = TempDir::new().unwrap();
+    let metadata_path = output_dir.path().join("release.json");
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--bump",
+            "auto",
+            "--release-metadata",
+            metadata_path.to_str().unwrap(),
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+
+    let metadata: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&metadata_path).unwrap()).unwrap();
+    assert_eq!(metadata["schema_version"], 1);
+    assert_eq!(metadata["tag"], "v1.3.0");
+    assert_eq!(metadata["previous_tag"], "v1.2.0");
+    assert_eq!(metadata["bump"], "minor");
+    let commits = metadata["commits"].as_array().unwrap();
+    assert_eq!(commits.len(), 2);
+    assert_eq!(commits[0]["sha"], feat.to_string());
+    assert_eq!(commits[0]["subject"], "feat: add arrays");
+    assert_eq!(commits[0]["author"], "Test User");
+    assert!(
+        feat.to_string()
+            .starts_with(commits[0]["short_sha"].as_str().unwrap())
+    );
+    assert_eq!(commits[1]["subject"], "fix: handle empty input");
+}
+
+#[test]
+fn test_ignore_merges() {
+    // main:    c1 (v1.0.0) -- fix -- merge ("feat!: ...")
+    // feature:   \-- f1 ("fix: ...") --/
+    let repo = TestRepo::new();
+    let c1 = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let f1 = repo.commit_with_parents(Some("refs/heads/feature"), "fix: tidy the parser", &[c1]);
+    let fix = repo.commit("fix: handle empty input");
+    repo.commit_with_parents(Some("HEAD"), "feat!: merge the redesign", &[fix, f1]);
+
+    let base_args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--bump",
+        "auto",
+        "--commit-counts",
+        "--breaking-since-stable",
+    ];
+
+    let result = run(repo.path(), &base_args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["breaking_count"], "1");
+    assert_eq!(result.outputs["next_tag"], "v2.0.0");
+
+    // Without the merge, only the fixes on both branches are left
+    let result = run(
+        repo.path(),
+        &[&base_args[..], &["--ignore-merges"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["breaking_count"], "0");
+    assert_eq!(result.outputs["feat_count"], "0");
+    assert_eq!(result.outputs["fix_count"], "2");
+    assert_eq!(result.outputs["breaking_since_stable"], "false");
+    assert_eq!(result.outputs["next_tag"], "v1.0.1");
+
+    let result = run(
+        repo.path(),
+        &[
+            &base_args[..],
+            &["--ignore-merges", "--range-bump", "v1.0.0..HEAD"],
+        ]
+        .concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["range_bump"], "patch");
+}
+
+#[test]
+fn test_within_days() {
+    let repo = TestRepo::new();
+    repo.commit_at("Ancient commit", 1_000_000_000);
+    repo.tag("v2.0.0");
+    repo.commit("Recent commit");
+    repo.tag("v1.0.0");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--within-days",
+            "30",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+    assert!(
+        result
+            .stdout
+            .contains("Skipping tag v2.0.0: its commit is not from the last 30 days"),
+        "{}",
+        result.stdout
+    );
+}
+
+#[test]
+fn test_release_line() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.3.9");
+    repo.tag("v1.4.2+build.5");
+
+    let result = run(
+        repo.path(),
+        &["--release-branch", "main", "--tag-prefix", "v"],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.4.2+build.5");
+    assert_eq!(result.outputs["release_line"], "1.4");
+}
+
+#[test]
+fn test_verify_message_version() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.annotated_tag(
+        "v1.2.0",
+        "Release v1.2.0\n\nThe 1.1.0 deprecations are removed",
+    );
+    repo.commit("Second commit");
+    repo.annotated_tag("v1.2.1", "Release v1.2.0\n\nCopied from the last release");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--verify-message-version",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains(
+            "The message of tag v1.2.1 names version 1.2.0, but the tag is version 1.2.1"
+        ),
+        "{}",
+        result.stderr
+    );
+
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--max-version", "1.2.0"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.2.0");
+
+    // Check that a message without a version fails too
+    repo.commit("Third commit");
+    repo.annotated_tag("v1.3.0", "Release notes");
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("The message of tag v1.3.0 does not name a version")
+    );
+}
+
+#[test]
+fn test_multiple_formats() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    let json_dir = TempDir::new().unwrap();
+    let json_path = json_dir.path().join("outputs.json");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--format",
+            "github,json",
+            "--output-file",
+            json_path.to_str().unwrap(),
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["latest_tag"], "v1.0.0");
+
+    // Each format other than github needs its own file
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--format",
+            "json",
+            "--format",
+            "gitlab-dotenv",
+            "--output-file",
+            json_path.to_str().unwrap(),
+        ],
+    );
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains(
+            "2 output files are needed for the formats other than github, but 1 were given"
+        ),
+        "{}",
+        result.stderr
+    );
+}
+
+#[test]
+fn test_validate_ordering() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0-rc.1");
+    repo.commit("Second commit");
+    repo.tag("v1.0.0");
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--validate-ordering",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("No prerelease is ranked above its stable release."),
+        "{}",
+        result.stdout
+    );
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_require_rc_before_stable() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0-rc.1");
+    repo.commit("Second commit");
+    repo.tag("v1.0.0");
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--require-rc-before-stable",
+    ];
+
+    // Every stable tag went through a release candidate
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+
+    // A stable tag without any prerelease of its version fails the check
+    repo.commit("Third commit");
+    repo.tag("v1.1.0");
+    let result = run(repo.path(), &args);
+    assert!(!result.success);
+    assert!(
+        result
+            .stderr
+            .contains("Stable tags were made without a prerelease of the same version: v1.1.0"),
+        "{}",
+        result.stderr
+    );
+}
+
+#[test]
+fn test_ref_namespace() {
+    let repo = TestRepo::new();
+    let head = repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+    repo.repo
+        .reference("refs/release-tags/v2.0.0", head, false, "test")
+        .unwrap();
+    let mut config = repo.repo.config().unwrap();
+    config.set_str("user.name", "Release Bot").unwrap();
+    config.set_str("user.email", "bot@example.com").unwrap();
+
+    let args = [
+        "--release-branch",
+        "main",
+        "--tag-prefix",
+        "v",
+        "--ref-namespace",
+        "refs/release-tags",
+    ];
+    let result = run(repo.path(), &args);
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v2.0.0");
+    assert_eq!(result.outputs["latest_tag_ref"], "refs/release-tags/v2.0.0");
+    assert_eq!(result.outputs["on_tag"], "true");
+
+    // Check that the new tag is created in the namespace too
+    let result = run(
+        repo.path(),
+        &[&args[..], &["--create-tag", "--bump", "patch", "--yes"]].concat(),
+    );
+    assert!(result.success, "{}", result.stderr);
+    let tag = repo
+        .repo
+        .find_reference("refs/release-tags/v2.0.1")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(tag.target_id(), head);
+    assert!(repo.repo.find_reference("refs/tags/v2.0.1").is_err());
+}
+
+#[test]
+fn test_multiline_output_framing() {
+    // A tag message that would end a fixed heredoc delimiter and inject another output
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    let message = "Release 1.0.0\nEOF\nlatest_tag=v9.9.9";
+    repo.annotated_tag("v1.0.0", message);
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--include-message",
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.output_file.contains("latest_tag_message<<EOF_1\n"));
+    assert_eq!(result.outputs["latest_tag_message"], message);
+    assert_eq!(result.outputs["latest_tag"], "v1.0.0");
+}
+
+#[test]
+fn test_yanked_file() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    for tag in ["v1.0.0", "v1.1.0", "v1.2.0"] {
+        repo.tag(tag);
+    }
+    let yanked = TempDir::new().unwrap();
+    let yanked_file = yanked.path().join("yanked.txt");
+    fs::write(&yanked_file, "# Broken releases\n1.2.0\n\n0.9.0\n").unwrap();
+
+    let result = run(
+        repo.path(),
+        &[
+            "--release-branch",
+            "main",
+            "--tag-prefix",
+            "v",
+            "--yanked-file",
+            yanked_file.to_str().unwrap(),
+        ],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert_eq!(result.outputs["latest_tag"], "v1.1.0");
+    assert_eq!(result.outputs["previous_stable"], "v1.0.0");
+    assert!(
+        result
+            .stdout
+            .contains("Skipping tag v1.2.0: version 1.2.0 is yanked")
+    );
+}
+
+#[test]
+fn test_color() {
+    let repo = TestRepo::new();
+    repo.commit("Initial commit");
+    repo.tag("v1.0.0");
+
+    let args = ["--release-branch", "main", "--tag-prefix", "v"];
+
+    let result = run(repo.path(), &[&args[..], &["--color", "never"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("Latest tag found: v1.0.0"));
+    assert!(!result.stdout.contains('\x1b'));
+    let plain_output_file = result.output_file;
+
+    let result = run(repo.path(), &[&args[..], &["--color", "always"]].concat());
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("\x1b["), "{}", result.stdout);
+    assert!(!result.output_file.contains('\x1b'));
+    assert_eq!(result.output_file, plain_output_file);
+}